@@ -1,32 +1,378 @@
 use std::env;
-use num_cpus;
-use rand::Rng;
+use std::fmt;
+use std::process;
+use rand::{Rng, SeedableRng};
+use rand::rngs::{SmallRng, StdRng};
 use rayon::prelude::*;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use rand::seq::SliceRandom;
+use rand::seq::index;
 use rayon::ThreadPoolBuilder;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use calamine::{Reader, Xlsx, open_workbook};
+use std::path::{Path, PathBuf};
+use std::mem;
+use std::thread;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use calamine::{Reader, Xlsx};
+
+const USAGE: &str = "\
+Usage: ArtificialBeeColony --input=<path> --output=<path> --config=<path> [options]
+
+Arguments:
+  --input=<path>      Input data file (.xlsx, .csv or TSPLIB .tsp), or - to read CSV from stdin
+  --output=<path>     Output file for the result
+  --config=<path>     Configuration file
+  --input-dir=<path>  Solve every .xlsx, .csv and .tsp file in a directory
+  --output-dir=<path> Directory for the results of --input-dir
+  --convert=<path>    Write the input data to <path> (.csv) instead of solving
+  --verify=<path>     Check a tour file against the input and print its length
+  --diff=<old>,<new>  Compare the best solutions of two output files
+  --baseline=random   Also report the best of as many random tours as ABC evaluated
+  --meta=<path>       Write the run metadata as JSON
+  --edge-freq=<count> Report the <count> most common edges in the final population
+  --labels-col=<n>    Read city labels from CSV column <n> and also print the tour with them
+  --precision=<n>     Print lengths with <n> decimal places
+  --limit-cities=<n>  Solve only the first <n> cities of the input
+  --sample-cities     With --limit-cities, take a random sample instead of the first cities
+  --gnuplot=<path>    Write the best solution's coordinates for Gnuplot
+  --history=<path>    Write per-iteration best length and acceptance rate as CSV
+  --iter-timing       With --history, add the wall time of every iteration
+  --seed-tour=<path>  Start from a previously found tour as one member of the population
+  --matrix=<path>     Optimize on this cost matrix instead of the distances between input cities
+  --edges=<path>      Optimize on a sparse graph given as from,to,distance rows
+  --dump-matrix=<path> Write the distance matrix used by the search as CSV
+  --focus=<path>      Concentrate early moves on the cities listed in <path>
+  --windows=<path>    Penalize arrivals outside per-city earliest,latest time windows
+  --population-out=<path> Write every final food source with its length as CSV
+  --population-in=<path> Start from the food sources in a --population-out file
+  --optimal-tour=<path> Report the gap of the best solution to a known optimal tour
+  --stream=<path>     Write one JSON line of search statistics per iteration while solving
+  --anytime[=<p>,...] Report the best length at percentages of max_iterations (default 10,25,50,100)
+  --log-interval=<n>  With --stream, only write every <n>-th iteration
+  --debug-invariants  Check the population for consistency after every iteration
+  --no-optimize       Report the length of one constructed tour without running ABC
+  --show-config       Print the configuration with all defaults resolved and exit
+  --autotune          Search a small grid of colony parameters and report the best configuration
+  --append            Append a CSV record to the output file instead of overwriting it
+  --progress          Show progress while solving
+  --stats             Add run statistics to the output
+  --output-inverse    Also write the position of each city in the best solution
+  --threads=<n>       Use <n> threads, overriding concurrent_count
+  --open-retries=<n>  Retry opening a locked input file <n> times (default 3)
+  --strict            Treat configuration warnings as errors
+  --help              Show this message
+";
+
+const AUTOTUNE_EVALUATIONS: usize = 20000;
+
+const PARALLEL_ARGMIN_THRESHOLD: usize = 4096;
+
+const KMEANS_ITERATIONS: usize = 20;
+
+const SPREAD_CANDIDATES: usize = 8;
+
+const SPATIAL_COLUMNS: [&str; 5] = ["x", "y", "z", "lat", "lon"];
+
+const PENALTY_SAMPLE_CITIES: usize = 1000;
+
+const TRIVIAL_CITY_AMOUNT: usize = 3;
+
+const ANYTIME_CHECKPOINTS: [f64; 4] = [10.0, 25.0, 50.0, 100.0];
+
+const SERIAL_WORK_THRESHOLD: usize = 10000;
+
+// Far above any global restart offset, so baseline tours never repeat the initial population.
+const BASELINE_SEED_OFFSET: u64 = 1 << 63;
+
+// TSPLIB truncates pi for GEO distances; using the exact constant changes some rounded lengths.
+#[allow(clippy::approx_constant)]
+const GEO_PI: f64 = 3.141592;
+
+const GEO_EARTH_RADIUS: f64 = 6378.388;
+
+const INVARIANT_TOLERANCE: f64 = 1e-9;
+
+const OPEN_RETRIES: usize = 3;
+
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+const RECORD_HEADER: &str = "timestamp,colony_size,candidate_amount,tournament_size,max_unimproved,max_iterations,improvement_threshold,concurrent_count,generation_method,best_solution_length,cost_time,best_solution\n";
 
 struct ArgumentKind {
     input: Option<String>,
     output: Option<String>,
     config: Option<String>,
+    input_dir: Option<String>,
+    output_dir: Option<String>,
+    convert: Option<String>,
+    verify: Option<String>,
+    diff: Option<String>,
+    baseline: Option<String>,
+    meta: Option<String>,
+    edge_freq: Option<usize>,
+    limit_cities: Option<usize>,
+    precision: Option<usize>,
+    labels_col: Option<usize>,
+    sample_cities: bool,
+    gnuplot: Option<String>,
+    history: Option<String>,
+    seed_tour: Option<String>,
+    dump_matrix: Option<String>,
+    matrix: Option<String>,
+    edges: Option<String>,
+    focus: Option<String>,
+    windows: Option<String>,
+    population_out: Option<String>,
+    population_in: Option<String>,
+    optimal_tour: Option<String>,
+    stream: Option<String>,
+    log_interval: usize,
+    open_retries: usize,
+    threads: Option<usize>,
+    anytime: Vec<f64>,
+    append: bool,
+    debug_invariants: bool,
+    autotune: bool,
+    no_optimize: bool,
+    show_config: bool,
+    iter_timing: bool,
+    progress: bool,
+    stats: bool,
+    output_inverse: bool,
+    strict: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum StopReason {
+    MaxIterations,
+    ImprovementThreshold,
+    EvaluationBudget,
+    Stagnant,
+    NoOptimize,
+    Trivial,
+    Degenerate,
+    Exhaustive,
+}
+
+#[derive(Debug)]
+enum TourError {
+    WrongLength { expected: usize, found: usize },
+    OutOfRange(usize),
+    Duplicate(usize),
+}
+
+impl fmt::Display for TourError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TourError::WrongLength { expected, found } => write!(f, "Tour has {} cities, expected {}.", found, expected),
+            TourError::OutOfRange(city) => write!(f, "City {} does not exist.", city),
+            TourError::Duplicate(city) => write!(f, "City {} is visited more than once.", city),
+        }
+    }
+}
+
+struct IterationKind {
+    iteration: usize,
+    best_tour_length: f64,
+    improved_sources: usize,
+    cost_time: Duration,
+}
+
+struct StreamKind {
+    file: File,
+    interval: usize,
+}
+
+struct ResultKind {
+    best_solution: Vec<usize>,
+    best_solution_length: f64,
+    solutions: Vec<Vec<usize>>,
+    initial_best_length: f64,
+    evaluations: usize,
+    stop_reason: StopReason,
+    history: Vec<IterationKind>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, PartialEq, Debug)]
 struct ConfigKind {
     colony_size: usize,
     candidate_amount: usize,
+    candidate_schedule: CandidateSchedule,
+    candidate_amount_end: Option<usize>,
+    tournament_size: usize,
+    onlooker_count: usize,
     max_unimproved: usize,
     max_iterations: usize,
     improvement_threshold: f64,
+    improvement_mode: ImprovementMode,
+    improvement_epsilon: f64,
     concurrent_count: usize,
+    par_chunk_size: Option<usize>,
+    force_serial: bool,
     generation_method: GenerationMethod,
+    init_method: InitMethod,
+    cluster_count: Option<usize>,
+    cluster_share: f64,
+    optimize: OptimizeDirection,
+    acceptance: AcceptanceMethod,
+    replacement: ReplacementMethod,
+    initial_temperature: f64,
+    cooling_rate: f64,
+    turn_angle: Option<f64>,
+    turn_penalty: f64,
+    window_penalty: f64,
+    penalty_scale: PenaltyScale,
+    constraint_handling: ConstraintHandling,
+    fixed_edge_penalty: f64,
+    distance_scale: f64,
+    columns: Vec<String>,
+    distance_unit: String,
+    require_improvement: bool,
+    elite_mutation_rate: f64,
+    replace_worst: bool,
+    max_matrix_bytes: Option<usize>,
+    matrix_mode: MatrixMode,
+    distance_metric: DistanceMetric,
+    distance_rounding: DistanceRounding,
+    normalize_coords: NormalizeMode,
+    global_restart_after: Option<usize>,
+    multistart: usize,
+    migration_interval: usize,
+    migration_count: usize,
+    fixed_edges: Vec<(usize, usize)>,
+    seed: Option<u64>,
+    max_evaluations: Option<usize>,
+    no_improve_secs: Option<f64>,
+    focus_cities: Vec<usize>,
+    focus_iterations: Option<usize>,
+    time_windows: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum InitMethod {
+    Random,
+    Cluster,
+    Spread,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ImprovementMode {
+    Relative,
+    Absolute,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum NormalizeMode {
+    None,
+    Center,
+    Standardize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DistanceMetric {
+    Euclidean,
+    Geo,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DistanceRounding {
+    None,
+    Nearest,
+    Ceil,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MatrixMode {
+    Dense,
+    Lazy,
+}
+
+// Symmetric holds the strict lower triangle of the city-to-city matrix row by row, n(n-1)/2 values.
+// Full holds every entry and is only used for asymmetric --matrix input. Lazy holds only the
+// coordinates and recomputes each distance on access, trading CPU time for O(n) instead of O(n²) memory.
+// Sparse holds only the listed edges of an --edges graph; every other pair costs missing_cost.
+enum DistanceMatrix {
+    Symmetric { city_amount: usize, lower_triangle: Vec<f64> },
+    Full(Vec<Vec<f64>>),
+    Lazy { cities: Vec<Vec<f64>>, metric: DistanceMetric, rounding: DistanceRounding },
+    Sparse { city_amount: usize, edges: HashMap<(usize, usize), f64>, missing_cost: f64 },
+}
+
+impl DistanceMatrix {
+    fn get(&self, city1: usize, city2: usize) -> f64 {
+        match self {
+            DistanceMatrix::Symmetric { lower_triangle, .. } => {
+                let (row, column) = (city1.max(city2), city1.min(city2));
+                if row == column {
+                    0.0
+                } else {
+                    lower_triangle[row * (row - 1) / 2 + column]
+                }
+            },
+            DistanceMatrix::Full(adjacency_matrix) => adjacency_matrix[city1][city2],
+            DistanceMatrix::Lazy { cities, metric, rounding } => city_distance(&cities[city1], &cities[city2], *metric, *rounding),
+            DistanceMatrix::Sparse { .. } if city1 == city2 => 0.0,
+            DistanceMatrix::Sparse { edges, missing_cost, .. } => edges.get(&(city1, city2)).copied().unwrap_or(*missing_cost),
+        }
+    }
+
+    // Sparse graphs may be directed, and the delta evaluation cannot cancel infinite missing edges.
+    fn is_symmetric(&self) -> bool {
+        !matches!(self, DistanceMatrix::Full(_) | DistanceMatrix::Sparse { .. })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            DistanceMatrix::Symmetric { city_amount, .. } => *city_amount,
+            DistanceMatrix::Full(adjacency_matrix) => adjacency_matrix.len(),
+            DistanceMatrix::Lazy { cities, .. } => cities.len(),
+            DistanceMatrix::Sparse { city_amount, .. } => *city_amount,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AcceptanceMethod {
+    Greedy,
+    SimulatedAnnealing,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PenaltyScale {
+    Absolute,
+    AverageEdge,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ConstraintHandling {
+    Repair,
+    Penalty,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CandidateSchedule {
+    Fixed,
+    Linear,
+    Exp,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ReplacementMethod {
+    SteadyState,
+    Generational,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum OptimizeDirection {
+    Min,
+    Max,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum GenerationMethod {
     None,
     Swap,
@@ -35,41 +381,195 @@ enum GenerationMethod {
     PartialShuffle,
 }
 
-fn get_arguments() -> ArgumentKind {
-    let mut arguments = ArgumentKind {
+fn default_arguments() -> ArgumentKind {
+    ArgumentKind {
         input: None,
         output: None,
         config: None,
-    };
-    let command_line: Vec<String> = env::args().collect();
-    for argument in &command_line[1..] {
+        input_dir: None,
+        output_dir: None,
+        convert: None,
+        verify: None,
+        diff: None,
+        baseline: None,
+        meta: None,
+        edge_freq: None,
+        limit_cities: None,
+        precision: None,
+        labels_col: None,
+        sample_cities: false,
+        gnuplot: None,
+        history: None,
+        seed_tour: None,
+        dump_matrix: None,
+        matrix: None,
+        edges: None,
+        focus: None,
+        windows: None,
+        population_out: None,
+        population_in: None,
+        optimal_tour: None,
+        stream: None,
+        log_interval: 1,
+        open_retries: OPEN_RETRIES,
+        threads: None,
+        anytime: Vec::new(),
+        append: false,
+        debug_invariants: false,
+        autotune: false,
+        no_optimize: false,
+        show_config: false,
+        iter_timing: false,
+        progress: false,
+        stats: false,
+        output_inverse: false,
+        strict: false,
+    }
+}
+
+// Takes the command line without the program name, e.g. env::args().skip(1).
+fn get_arguments(command_line: impl IntoIterator<Item = String>) -> ArgumentKind {
+    let mut arguments = default_arguments();
+    for argument in command_line {
         let parts: Vec<&str> = argument.splitn(2, '=').collect();
-        if parts.len() != 2 {
-            panic!("Invalid argument.");
-        }
         let key = parts[0];
+        if parts.len() == 1 {
+            match key {
+                "--append" => arguments.append = true,
+                "--autotune" => arguments.autotune = true,
+                "--no-optimize" => arguments.no_optimize = true,
+                "--show-config" => arguments.show_config = true,
+                "--iter-timing" => arguments.iter_timing = true,
+                "--anytime" => arguments.anytime = ANYTIME_CHECKPOINTS.to_vec(),
+                "--debug-invariants" => arguments.debug_invariants = true,
+                "--sample-cities" => arguments.sample_cities = true,
+                "--progress" => arguments.progress = true,
+                "--stats" => arguments.stats = true,
+                "--output-inverse" => arguments.output_inverse = true,
+                "--strict" => arguments.strict = true,
+                "--help" | "-h" => {
+                    print!("{}", USAGE);
+                    process::exit(0);
+                },
+                _ => panic!("Invalid argument: {}\n{}", argument, USAGE),
+            }
+            continue;
+        }
         let value = parts[1].trim_matches('"').trim_matches('\'');
         match key {
             "--input" => arguments.input = Some(value.to_string()),
             "--output" => arguments.output = Some(value.to_string()),
             "--config" => arguments.config = Some(value.to_string()),
-            _ => panic!("Unknown argument."),
+            "--input-dir" => arguments.input_dir = Some(value.to_string()),
+            "--output-dir" => arguments.output_dir = Some(value.to_string()),
+            "--convert" => arguments.convert = Some(value.to_string()),
+            "--verify" => arguments.verify = Some(value.to_string()),
+            "--diff" => arguments.diff = Some(value.to_string()),
+            "--baseline" => arguments.baseline = Some(value.to_string()),
+            "--meta" => arguments.meta = Some(value.to_string()),
+            "--gnuplot" => arguments.gnuplot = Some(value.to_string()),
+            "--history" => arguments.history = Some(value.to_string()),
+            "--seed-tour" => arguments.seed_tour = Some(value.to_string()),
+            "--dump-matrix" => arguments.dump_matrix = Some(value.to_string()),
+            "--matrix" => arguments.matrix = Some(value.to_string()),
+            "--edges" => arguments.edges = Some(value.to_string()),
+            "--stream" => arguments.stream = Some(value.to_string()),
+            "--focus" => arguments.focus = Some(value.to_string()),
+            "--windows" => arguments.windows = Some(value.to_string()),
+            "--population-out" => arguments.population_out = Some(value.to_string()),
+            "--population-in" => arguments.population_in = Some(value.to_string()),
+            "--optimal-tour" => arguments.optimal_tour = Some(value.to_string()),
+            "--log-interval" => arguments.log_interval = match value.parse::<usize>() {
+                Ok(log_interval) if log_interval >= 1 => log_interval,
+                _ => panic!("Invalid argument: --log-interval."),
+            },
+            "--anytime" => arguments.anytime = value
+                .split(',')
+                .map(|checkpoint| match checkpoint.trim().parse::<f64>() {
+                    Ok(checkpoint) if checkpoint > 0.0 && checkpoint <= 100.0 => checkpoint,
+                    _ => panic!("Invalid argument: --anytime.\nReason: Checkpoints are percentages of max_iterations between 0 and 100."),
+                })
+                .collect(),
+            "--threads" => arguments.threads = match value.parse::<usize>() {
+                Ok(threads) if threads >= 1 => Some(threads),
+                _ => panic!("Invalid argument: --threads."),
+            },
+            "--edge-freq" => arguments.edge_freq = Some(value.parse::<usize>().expect("Invalid argument: --edge-freq.")),
+            "--labels-col" => arguments.labels_col = match value.parse::<usize>() {
+                Ok(labels_col) if labels_col >= 1 => Some(labels_col),
+                _ => panic!("Invalid argument: --labels-col."),
+            },
+            "--precision" => arguments.precision = Some(value.parse::<usize>().expect("Invalid argument: --precision.")),
+            "--open-retries" => arguments.open_retries = value.parse::<usize>().expect("Invalid argument: --open-retries."),
+            "--limit-cities" => arguments.limit_cities = Some(value.parse::<usize>().expect("Invalid argument: --limit-cities.")),
+            _ => panic!("Unknown argument: {}\n{}", argument, USAGE),
         }
     }
     arguments
 }
 
-fn read_xlsx(input_path: String) -> Vec<Vec<f64>> {
+// Windows reports a file held open by another program, e.g. a workbook open in Excel, as a sharing
+// or lock violation; those clear up once the program lets go of the file, so they are worth retrying.
+fn is_transient_open_error(error: &io::Error) -> bool {
+    let locked = cfg!(windows) && matches!(error.raw_os_error(), Some(32) | Some(33));
+    locked || matches!(error.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::ResourceBusy)
+}
+
+// Calls open until it succeeds, retrying transient failures up to open_retries times with a doubling delay.
+fn retry_open<T>(input_path: &str, open_retries: usize, mut open: impl FnMut() -> io::Result<T>) -> T {
+    let mut delay = OPEN_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match open() {
+            Ok(opened) => return opened,
+            Err(e) if is_transient_open_error(&e) && attempt < open_retries => {
+                eprintln!("Warning: Cannot open {} yet, retrying in {}ms.", input_path, delay.as_millis());
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            },
+            Err(e) if is_transient_open_error(&e) => panic!("Cannot open file: {}\nReason: {}\nThe file may be open in another program such as Excel. Close it and try again.", input_path, e),
+            Err(e) => panic!("Cannot open file: {}\nReason: {}", input_path, e),
+        }
+    }
+}
+
+fn open_input_file(input_path: &str, open_retries: usize) -> File {
+    retry_open(input_path, open_retries, || File::open(input_path))
+}
+
+fn read_input_file(input_path: &str, open_retries: usize) -> String {
+    let mut content = String::new();
+    if let Err(e) = open_input_file(input_path, open_retries).read_to_string(&mut content) {
+        panic!("Cannot read file: {}\nReason: {}", input_path, e);
+    }
+    content
+}
+
+fn open_input_workbook(input_path: &str, open_retries: usize) -> Xlsx<BufReader<File>> {
+    Xlsx::new(BufReader::new(open_input_file(input_path, open_retries))).expect("Cannot open file.")
+}
+
+fn read_xlsx(input_path: String, open_retries: usize) -> Vec<Vec<f64>> {
+    let xlsx_data = parse_xlsx(input_path, open_retries);
+    validate_cities(&xlsx_data);
+    xlsx_data
+}
+
+// Numbers stored as text, such as "inf" in a --matrix sheet, are read like numeric cells.
+fn parse_xlsx(input_path: String, open_retries: usize) -> Vec<Vec<f64>> {
     let mut xlsx_data: Vec<Vec<f64>> = Vec::new();
-    let mut xlsx_file: Xlsx<_> = open_workbook(input_path).expect("Cannot open file.");
-    let sheet_name = xlsx_file.sheet_names().get(0).expect("No data sheet found.").clone();
+    let mut xlsx_file = open_input_workbook(&input_path, open_retries);
+    let sheet_name = xlsx_file.sheet_names().first().expect("No data sheet found.").clone();
     if let Some(Ok(sheet)) = xlsx_file.worksheet_range(sheet_name.as_str()) {
         for row in sheet.rows() {
             let mut row_data: Vec<f64> = Vec::new();
-            for col in row.iter() {
+            let row_width = row.iter().rposition(|col| *col != calamine::DataType::Empty).map_or(0, |index| index + 1);
+            for col in row[..row_width].iter() {
                 let col_data = match col {
                     calamine::DataType::Int(i) => *i as f64,
                     calamine::DataType::Float(f) => *f,
+                    calamine::DataType::String(text) => text.trim().parse::<f64>().expect("Invalid value in data sheet."),
                     _ => panic!("Invalid value in data sheet."),
                 };
                 row_data.push(col_data);
@@ -77,19 +577,319 @@ fn read_xlsx(input_path: String) -> Vec<Vec<f64>> {
             xlsx_data.push(row_data);
         }
     }
+    while xlsx_data.last().is_some_and(|row| row.is_empty()) {
+        xlsx_data.pop();
+    }
     xlsx_data
 }
 
-fn read_config(config_path: String) -> ConfigKind {
-    let mut config = ConfigKind {
+fn validate_cities(cities: &[Vec<f64>]) {
+    validate_rows(cities);
+    for (index, row) in cities.iter().enumerate() {
+        if let Some(col) = row.iter().position(|value| !value.is_finite()) {
+            panic!("Invalid value in data sheet.\nReason: Row {} column {} is not a finite number.", index + 1, col + 1);
+        }
+    }
+}
+
+// The shape checks alone, for sheets that may hold infinite values.
+fn validate_rows(cities: &[Vec<f64>]) {
+    let dimension = match cities.first() {
+        Some(row) if !row.is_empty() => row.len(),
+        _ => panic!("No data in data sheet."),
+    };
+    if cities.len() < 2 {
+        panic!("Invalid data sheet.\nReason: At least two cities are required.");
+    }
+    if let Some(index) = cities.iter().position(|row| row.len() != dimension) {
+        panic!("Invalid data sheet.\nReason: Row {} has {} columns, expected {}.", index + 1, cities[index].len(), dimension);
+    }
+}
+
+fn read_csv(csv_content: &str) -> Vec<Vec<f64>> {
+    let csv_data = parse_csv(csv_content);
+    validate_cities(&csv_data);
+    csv_data
+}
+
+fn parse_csv(csv_content: &str) -> Vec<Vec<f64>> {
+    let mut csv_data: Vec<Vec<f64>> = Vec::new();
+    for line in csv_content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let row_data: Vec<f64> = line
+            .split(',')
+            .map(|col| col.trim().parse::<f64>().expect("Invalid value in data sheet."))
+            .collect();
+        csv_data.push(row_data);
+    }
+    csv_data
+}
+
+fn read_labeled_csv(csv_content: &str, labels_col: usize) -> (Vec<Vec<f64>>, Vec<String>) {
+    let mut csv_data: Vec<Vec<f64>> = Vec::new();
+    let mut labels: Vec<String> = Vec::new();
+    for (index, line) in csv_content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut cols: Vec<&str> = line.split(',').map(|col| col.trim()).collect();
+        if labels_col > cols.len() {
+            panic!("Invalid data sheet.\nReason: Row {} has no column {} for labels.", index + 1, labels_col);
+        }
+        labels.push(cols.remove(labels_col - 1).to_string());
+        let row_data: Vec<f64> = cols
+            .iter()
+            .map(|col| col.parse::<f64>().expect("Invalid value in data sheet."))
+            .collect();
+        csv_data.push(row_data);
+    }
+    validate_cities(&csv_data);
+    (csv_data, labels)
+}
+
+fn read_stream_content(mut reader: impl BufRead) -> String {
+    let mut stream_content: Vec<u8> = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut stream_content) {
+        panic!("Failed to read stdin.\nReason: {}", e);
+    }
+    if stream_content.starts_with(b"PK\x03\x04") {
+        panic!("XLSX input cannot be read from stdin, use CSV instead.");
+    }
+    String::from_utf8(stream_content).expect("Invalid value in data sheet.")
+}
+
+fn read_stdin_content() -> String {
+    read_stream_content(io::stdin().lock())
+}
+
+fn read_stdin() -> Vec<Vec<f64>> {
+    read_csv(&read_stdin_content())
+}
+
+fn file_extension(file_path: impl AsRef<Path>) -> Option<String> {
+    file_path.as_ref().extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase())
+}
+
+fn read_input(input_path: String, open_retries: usize) -> Vec<Vec<f64>> {
+    if input_path == "-" {
+        return read_stdin();
+    }
+    match file_extension(&input_path).as_deref() {
+        Some("csv") => read_csv(&read_input_file(&input_path, open_retries)),
+        Some("tsp") => read_tsplib(&read_input_file(&input_path, open_retries)),
+        _ => read_xlsx(input_path, open_retries),
+    }
+}
+
+fn tsplib_header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(line_key, _)| line_key.trim() == key)
+        .map(|(_, value)| value.trim())
+}
+
+// The distance settings that reproduce a TSPLIB EDGE_WEIGHT_TYPE, or None for an unsupported type.
+fn tsplib_distance(edge_weight_type: &str) -> Option<(DistanceMetric, DistanceRounding)> {
+    match edge_weight_type {
+        "EUC_2D" => Some((DistanceMetric::Euclidean, DistanceRounding::Nearest)),
+        "CEIL_2D" => Some((DistanceMetric::Euclidean, DistanceRounding::Ceil)),
+        "GEO" => Some((DistanceMetric::Geo, DistanceRounding::None)),
+        _ => None,
+    }
+}
+
+// TSPLIB instances (.tsp) list 1-based nodes with their coordinates after NODE_COORD_SECTION,
+// terminated by EOF or the end of the file.
+fn read_tsplib(content: &str) -> Vec<Vec<f64>> {
+    let (header, node_section) = content
+        .split_once("NODE_COORD_SECTION")
+        .unwrap_or_else(|| panic!("Invalid TSPLIB file.\nReason: Only instances with a NODE_COORD_SECTION are supported."));
+    let edge_weight_type = tsplib_header_value(header, "EDGE_WEIGHT_TYPE").unwrap_or("");
+    if tsplib_distance(edge_weight_type).is_none() {
+        panic!("Invalid TSPLIB file.\nReason: EDGE_WEIGHT_TYPE {} is not supported, use EUC_2D, CEIL_2D or GEO.", edge_weight_type);
+    }
+    let cities: Vec<Vec<f64>> = node_section
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .take_while(|line| *line != "EOF")
+        .enumerate()
+        .map(|(index, line)| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 || fields[0].parse::<usize>() != Ok(index + 1) {
+                panic!("Invalid TSPLIB file.\nReason: Line {} of NODE_COORD_SECTION is not node {} with two coordinates.", index + 1, index + 1);
+            }
+            fields[1..]
+                .iter()
+                .map(|field| field.parse::<f64>().unwrap_or_else(|_| panic!("Invalid TSPLIB file.\nReason: {} is not a coordinate.", field)))
+                .collect()
+        })
+        .collect();
+    if let Some(dimension) = tsplib_header_value(header, "DIMENSION") {
+        if dimension.parse::<usize>() != Ok(cities.len()) {
+            panic!("Invalid TSPLIB file.\nReason: DIMENSION is {} but NODE_COORD_SECTION lists {} nodes.", dimension, cities.len());
+        }
+    }
+    validate_cities(&cities);
+    cities
+}
+
+// A .tsp file names its distance, so a config that computes another one is almost always a mistake.
+fn check_tsplib_distance(input_path: &str, config: &ConfigKind, open_retries: usize) {
+    if input_path == "-" || file_extension(input_path).as_deref() != Some("tsp") {
+        return;
+    }
+    let content = read_input_file(input_path, open_retries);
+    let edge_weight_type = tsplib_header_value(content.split("NODE_COORD_SECTION").next().unwrap_or(""), "EDGE_WEIGHT_TYPE").unwrap_or("");
+    if let Some((metric, rounding)) = tsplib_distance(edge_weight_type) {
+        if (config.distance_metric, config.distance_rounding) != (metric, rounding) {
+            eprintln!("Warning: {} has EDGE_WEIGHT_TYPE {}, which is distance_metric = {:?} with distance_rounding = {:?}; lengths will not match published results.", input_path, edge_weight_type, metric, rounding);
+        }
+    }
+}
+
+// Like read_input, but infinite values are kept for the caller to check.
+fn read_rows(input_path: String, open_retries: usize) -> Vec<Vec<f64>> {
+    let rows = if input_path == "-" {
+        parse_csv(&read_stdin_content())
+    } else if file_extension(&input_path).as_deref() == Some("csv") {
+        parse_csv(&read_input_file(&input_path, open_retries))
+    } else {
+        parse_xlsx(input_path, open_retries)
+    };
+    validate_rows(&rows);
+    rows
+}
+
+fn read_labeled_input(input_path: String, labels_col: usize, open_retries: usize) -> (Vec<Vec<f64>>, Vec<String>) {
+    if input_path == "-" {
+        return read_labeled_csv(&read_stdin_content(), labels_col);
+    }
+    match file_extension(&input_path).as_deref() {
+        Some("csv") => read_labeled_csv(&read_input_file(&input_path, open_retries), labels_col),
+        _ => panic!("Invalid argument: --labels-col is only supported for CSV input."),
+    }
+}
+
+fn read_cells(input_path: String, open_retries: usize) -> Vec<Vec<String>> {
+    let split_lines = |content: &str| -> Vec<Vec<String>> {
+        content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.split(',').map(|col| col.trim().to_string()).collect())
+            .collect()
+    };
+    if input_path == "-" {
+        return split_lines(&read_stdin_content());
+    }
+    match file_extension(&input_path).as_deref() {
+        Some("csv") => return split_lines(&read_input_file(&input_path, open_retries)),
+        Some("tsp") => panic!("Invalid config.\nReason: columns are not supported for TSPLIB input, whose nodes always have two coordinates."),
+        _ => {},
+    }
+    let mut xlsx_file = open_input_workbook(&input_path, open_retries);
+    let sheet_name = xlsx_file.sheet_names().first().expect("No data sheet found.").clone();
+    let mut rows: Vec<Vec<String>> = match xlsx_file.worksheet_range(sheet_name.as_str()) {
+        Some(Ok(sheet)) => sheet.rows().map(|row| row.iter().map(|col| col.to_string()).collect()).collect(),
+        _ => Vec::new(),
+    };
+    while rows.last().is_some_and(|row| row.iter().all(|col| col.is_empty())) {
+        rows.pop();
+    }
+    rows
+}
+
+// Reads the input through a columns mapping: x, y, z, lat and lon are coordinates in the listed order,
+// id is the label column and any other name is metadata that is read past.
+fn read_mapped_input(input_path: String, columns: &[String], open_retries: usize) -> (Vec<Vec<f64>>, Option<Vec<String>>) {
+    let label_col = columns.iter().position(|column| column == "id");
+    let coordinate_cols: Vec<usize> = (0..columns.len()).filter(|&col| SPATIAL_COLUMNS.contains(&columns[col].as_str())).collect();
+    let mut cities: Vec<Vec<f64>> = Vec::new();
+    let mut labels: Vec<String> = Vec::new();
+    for (index, row) in read_cells(input_path, open_retries).iter().enumerate() {
+        if row.len() < columns.len() {
+            panic!("Invalid data sheet.\nReason: Row {} has {} columns, but columns lists {}.", index + 1, row.len(), columns.len());
+        }
+        let city: Vec<f64> = coordinate_cols
+            .iter()
+            .map(|&col| match row[col].parse::<f64>() {
+                Ok(coordinate) => coordinate,
+                Err(_) => panic!("Invalid value in data sheet.\nReason: Row {} column {} ({}) is not a number.", index + 1, col + 1, columns[col]),
+            })
+            .collect();
+        cities.push(city);
+        if let Some(label_col) = label_col {
+            labels.push(row[label_col].clone());
+        }
+    }
+    validate_cities(&cities);
+    (cities, label_col.map(|_| labels))
+}
+
+fn default_config() -> ConfigKind {
+    ConfigKind {
         colony_size: 0,
         candidate_amount: 0,
+        candidate_schedule: CandidateSchedule::Fixed,
+        candidate_amount_end: None,
+        tournament_size: 2,
+        onlooker_count: 1,
         max_unimproved: 0,
         max_iterations: 0,
         improvement_threshold: 0.0,
+        improvement_mode: ImprovementMode::Relative,
+        improvement_epsilon: 1e-9,
         concurrent_count: 0,
         generation_method: GenerationMethod::None,
-    };
+        init_method: InitMethod::Random,
+        cluster_count: None,
+        par_chunk_size: None,
+        force_serial: false,
+        cluster_share: 0.5,
+        optimize: OptimizeDirection::Min,
+        acceptance: AcceptanceMethod::Greedy,
+        replacement: ReplacementMethod::SteadyState,
+        initial_temperature: 1.0,
+        cooling_rate: 0.99,
+        turn_angle: None,
+        turn_penalty: 0.0,
+        window_penalty: 1000.0,
+        penalty_scale: PenaltyScale::Absolute,
+        constraint_handling: ConstraintHandling::Repair,
+        fixed_edge_penalty: 1000.0,
+        distance_scale: 1.0,
+        columns: Vec::new(),
+        distance_unit: String::new(),
+        require_improvement: false,
+        elite_mutation_rate: 0.0,
+        replace_worst: false,
+        max_matrix_bytes: None,
+        matrix_mode: MatrixMode::Dense,
+        normalize_coords: NormalizeMode::None,
+        distance_metric: DistanceMetric::Euclidean,
+        distance_rounding: DistanceRounding::None,
+        global_restart_after: None,
+        multistart: 1,
+        migration_interval: 50,
+        migration_count: 1,
+        fixed_edges: Vec::new(),
+        seed: None,
+        max_evaluations: None,
+        no_improve_secs: None,
+        focus_cities: Vec::new(),
+        focus_iterations: None,
+        time_windows: Vec::new(),
+    }
+}
+
+fn read_config(config_path: String) -> ConfigKind {
+    let mut config = default_config();
     let config_file = File::open(config_path).expect("Fail read config file.");
     let reader = BufReader::new(config_file);
     for line in reader.lines() {
@@ -104,11 +904,35 @@ fn read_config(config_path: String) -> ConfigKind {
                         "Default" => 0,
                         _ => value.parse::<usize>().expect("Invalid configuration."),
                     },
+                    "candidate_schedule" => config.candidate_schedule = match value {
+                        "Fixed" => CandidateSchedule::Fixed,
+                        "Linear" => CandidateSchedule::Linear,
+                        "Exp" => CandidateSchedule::Exp,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "candidate_amount_end" => config.candidate_amount_end = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    },
+                    "tournament_size" => config.tournament_size = match value {
+                        "Default" => 2,
+                        _ => value.parse::<usize>().expect("Invalid configuration."),
+                    },
+                    "onlooker_count" => config.onlooker_count = match value {
+                        "Default" => 1,
+                        _ => value.parse::<usize>().expect("Invalid configuration."),
+                    },
                     "max_unimproved" => config.max_unimproved = value.parse::<usize>().expect("Invalid configuration."),
                     "max_iterations" => config.max_iterations = value.parse::<usize>().expect("Invalid configuration."),
                     "improvement_threshold" => config.improvement_threshold = value.parse::<f64>().expect("Invalid configuration."),
+                    "improvement_epsilon" => config.improvement_epsilon = value.parse::<f64>().expect("Invalid configuration."),
+                    "improvement_mode" => config.improvement_mode = match value {
+                        "Relative" => ImprovementMode::Relative,
+                        "Absolute" => ImprovementMode::Absolute,
+                        _ => panic!("Unknown configuration."),
+                    },
                     "concurrent_count" => config.concurrent_count = match value {
-                        "Default" => num_cpus::get(),
+                        "Default" => 0,
                         _ => value.parse::<usize>().expect("Invalid configuration."),
                     },
                     "generation_method" => config.generation_method = match value {
@@ -118,6 +942,110 @@ fn read_config(config_path: String) -> ConfigKind {
                         "PartialShuffle" => GenerationMethod::PartialShuffle,
                         _ => panic!("Unknown configuration."),
                     },
+                    "init_method" => config.init_method = match value {
+                        "Random" => InitMethod::Random,
+                        "Cluster" => InitMethod::Cluster,
+                        "Spread" => InitMethod::Spread,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "cluster_count" => config.cluster_count = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    },
+                    "force_serial" => config.force_serial = value.parse::<bool>().expect("Invalid configuration."),
+                    "par_chunk_size" => config.par_chunk_size = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    },
+                    "cluster_share" => config.cluster_share = value.parse::<f64>().expect("Invalid configuration."),
+                    "global_restart_after" => config.global_restart_after = Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    "multistart" => config.multistart = value.parse::<usize>().expect("Invalid configuration."),
+                    "migration_interval" => config.migration_interval = value.parse::<usize>().expect("Invalid configuration."),
+                    "migration_count" => config.migration_count = value.parse::<usize>().expect("Invalid configuration."),
+                    "max_evaluations" => config.max_evaluations = Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    "focus_iterations" => config.focus_iterations = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    },
+                    "no_improve_secs" => config.no_improve_secs = Some(value.parse::<f64>().expect("Invalid configuration.")),
+                    "seed" => config.seed = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<u64>().expect("Invalid configuration.")),
+                    },
+                    "fixed_edges" => config.fixed_edges = value
+                        .split(',')
+                        .filter(|edge| !edge.trim().is_empty())
+                        .map(|edge| {
+                            let cities: Vec<usize> = edge.split('-').map(|city| city.trim().parse::<usize>().expect("Invalid configuration.")).collect();
+                            match cities[..] {
+                                [city1, city2] => (city1, city2),
+                                _ => panic!("Invalid configuration."),
+                            }
+                        })
+                        .collect(),
+                    "optimize" => config.optimize = match value {
+                        "Min" => OptimizeDirection::Min,
+                        "Max" => OptimizeDirection::Max,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "acceptance" => config.acceptance = match value {
+                        "Greedy" => AcceptanceMethod::Greedy,
+                        "SimulatedAnnealing" => AcceptanceMethod::SimulatedAnnealing,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "replacement" => config.replacement = match value {
+                        "SteadyState" => ReplacementMethod::SteadyState,
+                        "Generational" => ReplacementMethod::Generational,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "initial_temperature" => config.initial_temperature = value.parse::<f64>().expect("Invalid configuration."),
+                    "cooling_rate" => config.cooling_rate = value.parse::<f64>().expect("Invalid configuration."),
+                    "turn_angle" => config.turn_angle = Some(value.parse::<f64>().expect("Invalid configuration.")),
+                    "turn_penalty" => config.turn_penalty = value.parse::<f64>().expect("Invalid configuration."),
+                    "window_penalty" => config.window_penalty = value.parse::<f64>().expect("Invalid configuration."),
+                    "penalty_scale" => config.penalty_scale = match value {
+                        "Absolute" => PenaltyScale::Absolute,
+                        "AverageEdge" => PenaltyScale::AverageEdge,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "constraint_handling" => config.constraint_handling = match value {
+                        "Repair" => ConstraintHandling::Repair,
+                        "Penalty" => ConstraintHandling::Penalty,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "fixed_edge_penalty" => config.fixed_edge_penalty = value.parse::<f64>().expect("Invalid configuration."),
+                    "distance_scale" => config.distance_scale = value.parse::<f64>().expect("Invalid configuration."),
+                    "distance_unit" => config.distance_unit = value.to_string(),
+                    "columns" => config.columns = value.split(',').map(|column| column.trim().to_lowercase()).collect(),
+                    "require_improvement" => config.require_improvement = value.parse::<bool>().expect("Invalid configuration."),
+                    "elite_mutation_rate" => config.elite_mutation_rate = value.parse::<f64>().expect("Invalid configuration."),
+                    "replace_worst" => config.replace_worst = value.parse::<bool>().expect("Invalid configuration."),
+                    "normalize_coords" => config.normalize_coords = match value {
+                        "None" => NormalizeMode::None,
+                        "Center" => NormalizeMode::Center,
+                        "Standardize" => NormalizeMode::Standardize,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "distance_metric" => config.distance_metric = match value {
+                        "Euclidean" => DistanceMetric::Euclidean,
+                        "Geo" => DistanceMetric::Geo,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "distance_rounding" => config.distance_rounding = match value {
+                        "None" => DistanceRounding::None,
+                        "Nearest" => DistanceRounding::Nearest,
+                        "Ceil" => DistanceRounding::Ceil,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "matrix_mode" => config.matrix_mode = match value {
+                        "Dense" => MatrixMode::Dense,
+                        "Lazy" => MatrixMode::Lazy,
+                        _ => panic!("Unknown configuration."),
+                    },
+                    "max_matrix_bytes" => config.max_matrix_bytes = match value {
+                        "Default" => None,
+                        _ => Some(value.parse::<usize>().expect("Invalid configuration.")),
+                    },
                     _ => panic!("Unknown configuration."),
                 }
             } else {
@@ -131,12 +1059,119 @@ fn read_config(config_path: String) -> ConfigKind {
         config.candidate_amount = config.colony_size / 2;
     }
     if config.concurrent_count == 0 {
-        config.concurrent_count = num_cpus::get();
+        config.concurrent_count = default_thread_count();
+    }
+    config
+}
+
+// RAYON_NUM_THREADS takes precedence over the detected CPU count, which can be wrong in containers.
+fn default_thread_count() -> usize {
+    match env::var("RAYON_NUM_THREADS").ok().and_then(|threads| threads.trim().parse::<usize>().ok()) {
+        Some(threads) if threads >= 1 => threads,
+        _ => num_cpus::get().max(1),
+    }
+}
+
+// --threads overrides concurrent_count, whether it was configured or detected.
+fn resolve_config(config_path: String, arguments: &ArgumentKind) -> ConfigKind {
+    let mut config = read_config(config_path);
+    if let Some(threads) = arguments.threads {
+        config.concurrent_count = threads;
     }
     config
 }
 
-fn euclidean_distance(city1: &Vec<f64>, city2: &Vec<f64>) -> f64 {
+fn format_config(config: &ConfigKind) -> Vec<(&'static str, String)> {
+    let mut config_entries = vec![
+        ("colony_size", config.colony_size.to_string()),
+        ("candidate_amount", config.candidate_amount.to_string()),
+        ("tournament_size", config.tournament_size.to_string()),
+        ("onlooker_count", config.onlooker_count.to_string()),
+        ("max_unimproved", config.max_unimproved.to_string()),
+        ("max_iterations", config.max_iterations.to_string()),
+        ("improvement_threshold", config.improvement_threshold.to_string()),
+        ("improvement_mode", format!("{:?}", config.improvement_mode)),
+        ("improvement_epsilon", config.improvement_epsilon.to_string()),
+        ("concurrent_count", config.concurrent_count.to_string()),
+        ("generation_method", format!("{:?}", config.generation_method)),
+        ("init_method", format!("{:?}", config.init_method)),
+        ("optimize", format!("{:?}", config.optimize)),
+        ("acceptance", format!("{:?}", config.acceptance)),
+        ("replacement", format!("{:?}", config.replacement)),
+        ("require_improvement", config.require_improvement.to_string()),
+        ("replace_worst", config.replace_worst.to_string()),
+        ("elite_mutation_rate", config.elite_mutation_rate.to_string()),
+        ("max_matrix_bytes", config.max_matrix_bytes.map_or("Default".to_string(), |max_matrix_bytes| max_matrix_bytes.to_string())),
+        ("matrix_mode", format!("{:?}", config.matrix_mode)),
+        ("normalize_coords", format!("{:?}", config.normalize_coords)),
+        ("distance_metric", format!("{:?}", config.distance_metric)),
+        ("distance_rounding", format!("{:?}", config.distance_rounding)),
+    ];
+    if config.candidate_schedule != CandidateSchedule::Fixed {
+        config_entries.push(("candidate_schedule", format!("{:?}", config.candidate_schedule)));
+        config_entries.push(("candidate_amount_end", config.candidate_amount_end.map_or("Default".to_string(), |candidate_amount_end| candidate_amount_end.to_string())));
+    }
+    if config.init_method == InitMethod::Cluster {
+        config_entries.push(("cluster_count", config.cluster_count.map_or("Default".to_string(), |cluster_count| cluster_count.to_string())));
+        config_entries.push(("cluster_share", config.cluster_share.to_string()));
+    }
+    if config.acceptance == AcceptanceMethod::SimulatedAnnealing {
+        config_entries.push(("initial_temperature", config.initial_temperature.to_string()));
+        config_entries.push(("cooling_rate", config.cooling_rate.to_string()));
+    }
+    if let Some(max_evaluations) = config.max_evaluations {
+        config_entries.push(("max_evaluations", max_evaluations.to_string()));
+    }
+    if let Some(focus_iterations) = config.focus_iterations {
+        config_entries.push(("focus_iterations", focus_iterations.to_string()));
+    }
+    if let Some(no_improve_secs) = config.no_improve_secs {
+        config_entries.push(("no_improve_secs", no_improve_secs.to_string()));
+    }
+    if config.force_serial {
+        config_entries.push(("force_serial", config.force_serial.to_string()));
+    }
+    if let Some(par_chunk_size) = config.par_chunk_size {
+        config_entries.push(("par_chunk_size", par_chunk_size.to_string()));
+    }
+    if let Some(seed) = config.seed {
+        config_entries.push(("seed", seed.to_string()));
+    }
+    if let Some(global_restart_after) = config.global_restart_after {
+        config_entries.push(("global_restart_after", global_restart_after.to_string()));
+    }
+    if config.multistart > 1 {
+        config_entries.push(("multistart", config.multistart.to_string()));
+        config_entries.push(("migration_interval", config.migration_interval.to_string()));
+        config_entries.push(("migration_count", config.migration_count.to_string()));
+    }
+    if !config.fixed_edges.is_empty() {
+        let edge_format: Vec<String> = config.fixed_edges.iter().map(|(city1, city2)| format!("{}-{}", city1, city2)).collect();
+        config_entries.push(("fixed_edges", edge_format.join(", ")));
+    }
+    if let Some(turn_angle) = config.turn_angle {
+        config_entries.push(("turn_angle", turn_angle.to_string()));
+    }
+    config_entries.push(("turn_penalty", config.turn_penalty.to_string()));
+    config_entries.push(("window_penalty", config.window_penalty.to_string()));
+    if config.penalty_scale != PenaltyScale::Absolute {
+        config_entries.push(("penalty_scale", format!("{:?}", config.penalty_scale)));
+    }
+    if config.constraint_handling != ConstraintHandling::Repair {
+        config_entries.push(("constraint_handling", format!("{:?}", config.constraint_handling)));
+        config_entries.push(("fixed_edge_penalty", config.fixed_edge_penalty.to_string()));
+    }
+    config_entries.push(("distance_scale", config.distance_scale.to_string()));
+    if !config.distance_unit.is_empty() {
+        config_entries.push(("distance_unit", config.distance_unit.clone()));
+    }
+    if !config.columns.is_empty() {
+        config_entries.push(("columns", config.columns.join(",")));
+    }
+    config_entries
+}
+
+fn euclidean_distance(city1: &[f64], city2: &[f64]) -> f64 {
     if city1.len() != city2.len() {
         panic!("Invalid data sheet.");
     }
@@ -147,64 +1182,958 @@ fn euclidean_distance(city1: &Vec<f64>, city2: &Vec<f64>) -> f64 {
     distance.sqrt()
 }
 
-fn calc_cities_distance(cities: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
-    let mut adjacency_matrix: Vec<Vec<f64>> = vec![vec![0.0; cities.len()]; cities.len()];
-    for i in 0..cities.len() {
-        for j in (i+1)..cities.len() {
-            let distance = euclidean_distance(&cities[i], &cities[j]);
-            adjacency_matrix[i][j] = distance;
-            adjacency_matrix[j][i] = distance;
-        }
+// TSPLIB's GEO distance in kilometres for cities given as latitude and longitude in DDD.MM format,
+// where the digits after the point are minutes. The constants and the truncations follow the spec
+// exactly so that published lengths are reproduced; it is not a true great-circle distance.
+fn geo_distance(city1: &[f64], city2: &[f64]) -> f64 {
+    if city1.len() != 2 || city2.len() != 2 {
+        panic!("Invalid data sheet.\nReason: distance_metric = Geo needs exactly a latitude and a longitude per city.");
     }
-    adjacency_matrix
+    let to_radians = |coordinate: f64| {
+        let degrees = coordinate.trunc();
+        GEO_PI * (degrees + 5.0 * (coordinate - degrees) / 3.0) / 180.0
+    };
+    let (latitude1, longitude1) = (to_radians(city1[0]), to_radians(city1[1]));
+    let (latitude2, longitude2) = (to_radians(city2[0]), to_radians(city2[1]));
+    let q1 = (longitude1 - longitude2).cos();
+    let q2 = (latitude1 - latitude2).cos();
+    let q3 = (latitude1 + latitude2).cos();
+    (GEO_EARTH_RADIUS * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
 }
 
-fn validate_config(config: &ConfigKind) {
-    if config.colony_size < 1 || (config.colony_size % 2) != 0 {
-        panic!("Invalid colony size.");
-    } else if config.max_unimproved < 1 {
-        panic!("Invalid unimproved times.");
-    } else if config.max_iterations < 1 {
-        panic!("Invalid iterations");
-    } else if config.improvement_threshold < 0.0 || config.improvement_threshold > 100.0 {
-        panic!("Invalid improvement threshold.");
-    } else if config.candidate_amount < 1 {
-        panic!("Invalid candidate amount.");
-    } else if config.concurrent_count < 1 {
-        panic!("Invalid concurrent count.");
-    } else if config.generation_method == GenerationMethod::None {
-        panic!("Invalid generation method.");
+// Nearest matches TSPLIB's EUC_2D, nint(x) = floor(x + 0.5), and Ceil matches CEIL_2D.
+fn city_distance(city1: &[f64], city2: &[f64], metric: DistanceMetric, rounding: DistanceRounding) -> f64 {
+    let distance = match metric {
+        DistanceMetric::Euclidean => euclidean_distance(city1, city2),
+        DistanceMetric::Geo => geo_distance(city1, city2),
+    };
+    match rounding {
+        DistanceRounding::None => distance,
+        DistanceRounding::Nearest => (distance + 0.5).floor(),
+        DistanceRounding::Ceil => distance.ceil(),
     }
 }
 
-fn initialize_solution(city_amount: usize) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
+fn available_memory() -> Option<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kilobytes = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    kilobytes.checked_mul(1024)
+}
+
+fn check_matrix_size(city_amount: usize, config: &ConfigKind) {
+    let limit = match config.max_matrix_bytes.or_else(available_memory) {
+        Some(limit) => limit,
+        None => return,
+    };
+    let matrix_bytes = city_amount.checked_mul(city_amount.saturating_sub(1)).and_then(|cells| (cells / 2).checked_mul(mem::size_of::<f64>()));
+    if matrix_bytes.is_none_or(|matrix_bytes| matrix_bytes > limit) {
+        let matrix_format = matrix_bytes.map_or("more than usize::MAX".to_string(), |matrix_bytes| matrix_bytes.to_string());
+        panic!("Distance matrix too large.\nReason: {} cities need {} bytes, the limit is {} bytes. Use a smaller instance or raise max_matrix_bytes.", city_amount, matrix_format, limit);
+    }
+}
+
+fn calc_cities_distance(cities: &[Vec<f64>], metric: DistanceMetric, rounding: DistanceRounding) -> DistanceMatrix {
+    let mut lower_triangle: Vec<f64> = Vec::with_capacity(cities.len() * cities.len().saturating_sub(1) / 2);
+    for i in 0..cities.len() {
+        for j in 0..i {
+            let distance = city_distance(&cities[i], &cities[j], metric, rounding);
+            if !distance.is_finite() {
+                panic!("Invalid distance.\nReason: Distance between city {} and city {} is not finite.", j, i);
+            }
+            lower_triangle.push(distance);
+        }
+    }
+    DistanceMatrix::Symmetric { city_amount: cities.len(), lower_triangle }
+}
+
+// Center subtracts the centroid; Standardize also divides by the root mean square distance to it,
+// one factor for all dimensions so the tour ranking is unchanged. Costs given in length units are
+// rescaled to match, and the returned configuration is meant for the search only.
+fn normalize_coordinates(cities: &[Vec<f64>], config: &ConfigKind) -> (Vec<Vec<f64>>, ConfigKind) {
+    if config.normalize_coords == NormalizeMode::None {
+        return (cities.to_vec(), config.clone());
+    }
+    let dimension_amount = cities[0].len();
+    let centroid: Vec<f64> = (0..dimension_amount)
+        .map(|dimension| cities.iter().map(|city| city[dimension]).sum::<f64>() / cities.len() as f64)
+        .collect();
+    let centered_cities: Vec<Vec<f64>> = cities
+        .iter()
+        .map(|city| city.iter().zip(&centroid).map(|(coordinate, center)| coordinate - center).collect())
+        .collect();
+    let spread = (centered_cities.iter().map(|city| city.iter().map(|coordinate| coordinate * coordinate).sum::<f64>()).sum::<f64>() / cities.len() as f64).sqrt();
+    if config.normalize_coords == NormalizeMode::Center || spread == 0.0 || !spread.is_finite() {
+        return (centered_cities, config.clone());
+    }
+    let normalized_cities = centered_cities
+        .into_iter()
+        .map(|city| city.into_iter().map(|coordinate| coordinate / spread).collect())
+        .collect();
+    let search_config = ConfigKind {
+        turn_penalty: config.turn_penalty / spread,
+        fixed_edge_penalty: config.fixed_edge_penalty / spread,
+        initial_temperature: config.initial_temperature / spread,
+        improvement_threshold: match config.improvement_mode {
+            ImprovementMode::Relative => config.improvement_threshold,
+            ImprovementMode::Absolute => config.improvement_threshold / spread,
+        },
+        ..config.clone()
+    };
+    (normalized_cities, search_config)
+}
+
+// Mean distance from a city to its nearest neighbour, a scale-free estimate of the length of a good
+// tour's edges. Large instances use an evenly spaced sample of cities to keep this O(n).
+fn mean_nearest_distance(distance: &DistanceMatrix) -> f64 {
+    let city_amount = distance.len();
+    let sample: Vec<usize> = (0..city_amount).step_by(city_amount.div_ceil(PENALTY_SAMPLE_CITIES)).collect();
+    let nearest_sum: f64 = sample
+        .iter()
+        .map(|&city1| (0..city_amount).filter(|&city2| city2 != city1).map(|city2| distance.get(city1, city2)).fold(f64::INFINITY, f64::min))
+        .sum();
+    nearest_sum / sample.len() as f64
+}
+
+// With AverageEdge, penalties are weights relative to the instance's typical edge length, measured on
+// the matrix the search actually uses, so any normalization of the coordinates is already included.
+fn scale_penalties(search_config: ConfigKind, config: &ConfigKind, distance: &DistanceMatrix) -> ConfigKind {
+    match config.penalty_scale {
+        PenaltyScale::Absolute => search_config,
+        PenaltyScale::AverageEdge => ConfigKind {
+            turn_penalty: config.turn_penalty * mean_nearest_distance(distance),
+            fixed_edge_penalty: config.fixed_edge_penalty * mean_nearest_distance(distance),
+            ..search_config
+        },
+    }
+}
+
+// The matrix covers every input city; only the rows and columns of kept_cities, the cities left
+// after --limit-cities, are used.
+// An off-diagonal +inf marks a forbidden edge; the diagonal is never used.
+fn read_distance_matrix(matrix_path: String, input_city_amount: usize, kept_cities: &[usize], open_retries: usize) -> DistanceMatrix {
+    let adjacency_matrix = read_rows(matrix_path, open_retries);
+    if adjacency_matrix.len() != input_city_amount || adjacency_matrix[0].len() != input_city_amount {
+        panic!("Invalid distance matrix.\nReason: The matrix is {}x{}, expected {}x{} for the input cities.", adjacency_matrix.len(), adjacency_matrix[0].len(), input_city_amount, input_city_amount);
+    }
+    for (city1, row) in adjacency_matrix.iter().enumerate() {
+        if let Some(city2) = (0..input_city_amount).find(|&city2| city2 != city1 && (row[city2].is_nan() || row[city2] == f64::NEG_INFINITY)) {
+            panic!("Invalid value in data sheet.\nReason: Row {} column {} is neither a finite number nor +inf.", city1 + 1, city2 + 1);
+        }
+    }
+    let adjacency_matrix: Vec<Vec<f64>> = kept_cities.iter().map(|&city1| kept_cities.iter().map(|&city2| adjacency_matrix[city1][city2]).collect()).collect();
+    let city_amount = kept_cities.len();
+    let symmetric = (0..city_amount).all(|city1| (0..city1).all(|city2| adjacency_matrix[city1][city2] == adjacency_matrix[city2][city1]));
+    if !symmetric {
+        return DistanceMatrix::Full(adjacency_matrix);
+    }
+    let lower_triangle = (0..city_amount).flat_map(|city1| adjacency_matrix[city1][..city1].to_vec()).collect();
+    DistanceMatrix::Symmetric { city_amount, lower_triangle }
+}
+
+// Reads from,to,distance rows with 0-based cities. An edge applies in both directions unless the
+// reverse direction is listed with its own distance.
+fn read_edge_list(edges_path: &str, city_amount: usize) -> HashMap<(usize, usize), f64> {
+    let edges_content = fs::read_to_string(edges_path).expect("Fail read edge list.");
+    let mut edges: HashMap<(usize, usize), f64> = HashMap::new();
+    for (index, line) in edges_content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let cols: Vec<&str> = line.split(',').map(|col| col.trim()).collect();
+        let edge = match cols[..] {
+            [city1, city2, length] => (city1.parse::<usize>(), city2.parse::<usize>(), length.parse::<f64>()),
+            _ => panic!("Invalid edge list.\nReason: Row {} does not have the form from,to,distance.", index + 1),
+        };
+        match edge {
+            (Ok(city1), Ok(city2), Ok(length)) if city1.max(city2) < city_amount && city1 != city2 && length.is_finite() && length >= 0.0 => {
+                edges.insert((city1, city2), length);
+            },
+            _ => panic!("Invalid edge list.\nReason: Row {} needs two different cities below {} and a finite, non-negative distance.", index + 1, city_amount),
+        }
+    }
+    let listed_edges: Vec<((usize, usize), f64)> = edges.iter().map(|(&edge, &length)| (edge, length)).collect();
+    for ((city1, city2), length) in listed_edges {
+        edges.entry((city2, city1)).or_insert(length);
+    }
+    edges
+}
+
+// For a matrix with forbidden (+inf) edges, returns a copy in which each costs more than all allowed
+// edges together, so the search first avoids forbidden edges and then minimizes the length, like it
+// does for --edges. Cities left with fewer than two allowed edges are reported.
+fn forbid_infinite_edges(distance: &DistanceMatrix) -> Option<DistanceMatrix> {
+    let city_amount = distance.len();
+    let off_diagonal = || (0..city_amount).flat_map(|city1| (0..city_amount).filter(move |&city2| city2 != city1).map(move |city2| (city1, city2)));
+    if off_diagonal().all(|(city1, city2)| distance.get(city1, city2).is_finite()) {
+        return None;
+    }
+    for city in 0..city_amount {
+        let allowed_edges = (0..city_amount).filter(|&other| other != city && distance.get(city, other).is_finite()).count();
+        if allowed_edges < 2 {
+            eprintln!("Warning: City {} has {} allowed edges in the matrix, so every tour uses a forbidden edge.", city, allowed_edges);
+        }
+    }
+    let forbidden_cost = off_diagonal().map(|(city1, city2)| distance.get(city1, city2)).filter(|length| length.is_finite()).sum::<f64>() + 1.0;
+    let replace = |length: f64| if length.is_finite() { length } else { forbidden_cost };
+    Some(match distance {
+        DistanceMatrix::Symmetric { city_amount, lower_triangle } => DistanceMatrix::Symmetric { city_amount: *city_amount, lower_triangle: lower_triangle.iter().map(|&length| replace(length)).collect() },
+        DistanceMatrix::Full(adjacency_matrix) => DistanceMatrix::Full(adjacency_matrix.iter().map(|row| row.iter().map(|&length| replace(length)).collect()).collect()),
+        _ => panic!("Unknown error."),
+    })
+}
+
+fn build_distance_matrix(cities: &[Vec<f64>], config: &ConfigKind) -> DistanceMatrix {
+    match config.matrix_mode {
+        MatrixMode::Dense => {
+            check_matrix_size(cities.len(), config);
+            calc_cities_distance(cities, config.distance_metric, config.distance_rounding)
+        },
+        MatrixMode::Lazy => {
+            // Lazy distances are not screened one by one like calc_cities_distance does, but no
+            // Euclidean distance exceeds the bounding-box diagonal, so checking that covers them all.
+            let diagonal = euclidean_distance(&vec![0.0; cities[0].len()], &calc_bounding_box(cities));
+            if config.distance_metric == DistanceMetric::Euclidean && !diagonal.is_finite() {
+                panic!("Invalid distance.\nReason: The coordinates span too wide a range for the distances between cities to be finite.");
+            }
+            DistanceMatrix::Lazy { cities: cities.to_vec(), metric: config.distance_metric, rounding: config.distance_rounding }
+        },
+    }
+}
+
+fn validate_config(config: &ConfigKind) {
+    if config.colony_size < 1 || !config.colony_size.is_multiple_of(2) {
+        panic!("Invalid colony size.");
+    } else if config.max_unimproved < 1 {
+        panic!("Invalid unimproved times.");
+    } else if config.max_iterations < 1 {
+        panic!("Invalid iterations");
+    } else if !config.improvement_threshold.is_finite() || config.improvement_threshold < 0.0 {
+        panic!("Invalid improvement threshold.");
+    } else if config.improvement_mode == ImprovementMode::Relative && config.improvement_threshold > 1.0 {
+        panic!("Invalid improvement threshold.\nReason: A relative threshold is a fraction between 0 and 1, use {} for {}%.", config.improvement_threshold / 100.0, config.improvement_threshold);
+    } else if !(0.0..1.0).contains(&config.improvement_epsilon) {
+        panic!("Invalid improvement epsilon.");
+    } else if config.candidate_amount < 1 {
+        panic!("Invalid candidate amount.");
+    } else if config.tournament_size < 2 || config.tournament_size > config.candidate_amount {
+        panic!("Invalid tournament size.");
+    } else if config.candidate_amount_end.is_some_and(|candidate_amount_end| candidate_amount_end < config.tournament_size) {
+        panic!("Invalid candidate amount end.\nReason: Every iteration needs at least tournament_size ({}) candidates.", config.tournament_size);
+    } else if config.onlooker_count < 1 {
+        panic!("Invalid onlooker count.");
+    } else if config.concurrent_count < 1 {
+        panic!("Invalid concurrent count.");
+    } else if config.generation_method == GenerationMethod::None {
+        panic!("Invalid generation method.");
+    } else if config.max_evaluations == Some(0) {
+        panic!("Invalid evaluation budget.");
+    } else if config.focus_iterations == Some(0) {
+        panic!("Invalid focus iterations.");
+    } else if config.no_improve_secs.is_some_and(|no_improve_secs| !no_improve_secs.is_finite() || no_improve_secs <= 0.0) {
+        panic!("Invalid no-improvement timeout.");
+    } else if config.global_restart_after == Some(0) {
+        panic!("Invalid global restart.");
+    } else if config.turn_angle.is_some_and(|turn_angle| !(0.0..180.0).contains(&turn_angle)) {
+        panic!("Invalid turn angle.");
+    } else if config.turn_penalty < 0.0 || (config.turn_penalty > 0.0 && (config.turn_angle.is_none() || config.optimize == OptimizeDirection::Max)) {
+        panic!("Invalid turn penalty.");
+    } else if !config.window_penalty.is_finite() || config.window_penalty < 0.0 {
+        panic!("Invalid window penalty.");
+    } else if !config.fixed_edge_penalty.is_finite() || config.fixed_edge_penalty < 0.0 {
+        panic!("Invalid fixed edge penalty.");
+    } else if config.constraint_handling == ConstraintHandling::Penalty && !config.fixed_edges.is_empty() && config.optimize == OptimizeDirection::Max {
+        panic!("Invalid constraint handling.\nReason: Penalties only push towards the fixed edges with optimize = Min.");
+    } else if !config.distance_scale.is_finite() || config.distance_scale <= 0.0 {
+        panic!("Invalid distance scale.");
+    } else if !config.initial_temperature.is_finite() || config.initial_temperature <= 0.0 {
+        panic!("Invalid initial temperature.");
+    } else if !(config.cooling_rate > 0.0 && config.cooling_rate < 1.0) {
+        panic!("Invalid cooling rate.");
+    } else if !(0.0..=1.0).contains(&config.elite_mutation_rate) {
+        panic!("Invalid elite mutation rate.");
+    } else if config.distance_rounding != DistanceRounding::None && config.normalize_coords == NormalizeMode::Standardize {
+        panic!("Invalid distance rounding.\nReason: Rounded distances need the original coordinates, so normalize_coords = Standardize cannot be used.");
+    } else if config.distance_metric == DistanceMetric::Geo && config.normalize_coords != NormalizeMode::None {
+        panic!("Invalid distance metric.\nReason: Geo distances need the original latitudes and longitudes, so normalize_coords cannot be used.");
+    } else if config.replacement == ReplacementMethod::Generational && (config.acceptance != AcceptanceMethod::Greedy || config.multistart > 1) {
+        panic!("Invalid replacement.\nReason: Generational replacement keeps the best tours of the whole population, so it needs acceptance = Greedy and multistart = 1.");
+    } else if !config.columns.is_empty() && !config.columns.iter().any(|column| SPATIAL_COLUMNS.contains(&column.as_str())) {
+        panic!("Invalid columns.\nReason: At least one of {} is needed as a coordinate.", SPATIAL_COLUMNS.join(", "));
+    } else if config.columns.iter().filter(|column| *column == "id").count() > 1 {
+        panic!("Invalid columns.\nReason: Only one column can be id.");
+    } else if config.par_chunk_size == Some(0) {
+        panic!("Invalid parallel chunk size.");
+    } else if config.cluster_count == Some(0) {
+        panic!("Invalid cluster count.");
+    } else if !(0.0..=1.0).contains(&config.cluster_share) {
+        panic!("Invalid cluster share.");
+    }
+    validate_islands(config);
+    build_chains(&config.fixed_edges);
+}
+
+// Checked again after cap_colony_size, since the islands have to fit the capped colony.
+fn validate_islands(config: &ConfigKind) {
+    if config.multistart < 1 || config.multistart > config.colony_size / 2 {
+        panic!("Invalid multistart.\nReason: Between 1 and colony_size / 2 islands are allowed.");
+    } else if config.migration_interval < 1 {
+        panic!("Invalid migration interval.");
+    } else if config.multistart > 1 && (config.migration_count < 1 || config.migration_count >= config.colony_size / 2 / config.multistart) {
+        panic!("Invalid migration count.\nReason: Each island must keep at least one food source of its own.");
+    }
+}
+
+fn count_distinct_tours(city_amount: usize, limit: usize) -> usize {
+    if city_amount <= 3 {
+        return 1;
+    }
+    let mut distinct_tours: usize = 3;
+    for city in 4..city_amount {
+        distinct_tours = distinct_tours.saturating_mul(city);
+        if distinct_tours >= limit {
+            return limit;
+        }
+    }
+    distinct_tours
+}
+
+fn cap_colony_size(config: &ConfigKind, city_amount: usize) -> ConfigKind {
+    let distinct_tours = count_distinct_tours(city_amount, config.colony_size / 2);
+    if config.colony_size / 2 <= distinct_tours {
+        return config.clone();
+    }
+    eprintln!("Warning: Only {} distinct tours exist for {} cities, colony size is capped at {}.", distinct_tours, city_amount, distinct_tours * 2);
+    // Every island has to keep migration_count + 1 food sources, so fewer islands fit the capped colony.
+    let multistart = config.multistart.min(distinct_tours / (config.migration_count + 1)).max(1);
+    if multistart < config.multistart {
+        eprintln!("Warning: multistart is lowered to {} to fit the capped colony.", multistart);
+    }
+    ConfigKind {
+        colony_size: distinct_tours * 2,
+        multistart,
+        ..config.clone()
+    }
+}
+
+fn check_colony_size(config: &ConfigKind, city_amount: usize, strict: bool) {
+    let suggested_size = ((2.0 * (city_amount as f64).sqrt()).ceil() as usize).next_multiple_of(2);
+    let suggested_size = suggested_size.min(count_distinct_tours(city_amount, suggested_size / 2) * 2);
+    if config.colony_size < suggested_size {
+        let message = format!("Colony size {} is small for {} cities, consider at least {}.", config.colony_size, city_amount, suggested_size);
+        if strict {
+            panic!("Invalid colony size.\nReason: {}", message);
+        }
+        eprintln!("Warning: {}", message);
+    }
+}
+
+fn initialize_solution(city_amount: usize, rng: &mut impl Rng) -> Vec<usize> {
     let mut solution: Vec<usize> = (0..city_amount).collect();
-    solution.shuffle(&mut rng);
+    solution.shuffle(rng);
+    solution
+}
+
+// On a sparse graph a uniformly random tour uses almost only missing edges, so the tour is walked along
+// listed edges instead, always to the unvisited neighbour with the fewest unvisited neighbours of its
+// own (ties at random) so that no city is left stranded, and jumping to a random unvisited city when
+// the walk is stuck. Dense distances keep the uniform shuffle.
+fn random_tour(distance: &DistanceMatrix, rng: &mut impl Rng) -> Vec<usize> {
+    let DistanceMatrix::Sparse { city_amount, edges, .. } = distance else {
+        return initialize_solution(distance.len(), rng);
+    };
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); *city_amount];
+    for &(city1, city2) in edges.keys() {
+        neighbours[city1].push(city2);
+    }
+    // The map's iteration order varies between runs, seeded walks must not.
+    neighbours.iter_mut().for_each(|city_neighbours| city_neighbours.sort_unstable());
+    let mut visited = vec![false; *city_amount];
+    let mut solution: Vec<usize> = Vec::with_capacity(*city_amount);
+    let mut city = rng.gen_range(0..*city_amount);
+    loop {
+        visited[city] = true;
+        solution.push(city);
+        if solution.len() == *city_amount {
+            return solution;
+        }
+        let unvisited_degree = |city: usize| neighbours[city].iter().filter(|&&neighbour| !visited[neighbour]).count();
+        let next_cities: Vec<usize> = neighbours[city].iter().copied().filter(|&neighbour| !visited[neighbour]).collect();
+        let next_cities: Vec<usize> = match next_cities.iter().map(|&neighbour| unvisited_degree(neighbour)).min() {
+            Some(fewest) => next_cities.into_iter().filter(|&neighbour| unvisited_degree(neighbour) == fewest).collect(),
+            None => (0..*city_amount).filter(|&city| !visited[city]).collect(),
+        };
+        city = *next_cities.choose(rng).unwrap();
+    }
+}
+
+fn seeded_solution(city_amount: usize, seed: u64, seed_offset: u64) -> Vec<usize> {
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(seed_offset));
+    initialize_solution(city_amount, &mut rng)
+}
+
+fn limit_cities(city_amount: usize, limit: usize, sample: bool, config: &ConfigKind) -> Vec<usize> {
+    if limit < 2 {
+        panic!("Invalid argument: --limit-cities must be at least 2.");
+    }
+    if limit >= city_amount {
+        return (0..city_amount).collect();
+    }
+    if !sample {
+        return (0..limit).collect();
+    }
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut sampled_index = index::sample(&mut rng, city_amount, limit).into_vec();
+    sampled_index.sort_unstable();
+    sampled_index
+}
+
+fn build_chains(fixed_edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(city1, city2) in fixed_edges {
+        if city1 == city2 {
+            panic!("Invalid fixed edges.\nReason: Edge {}-{} connects a city to itself.", city1, city2);
+        }
+        if neighbors.get(&city1).is_some_and(|neighbor| neighbor.contains(&city2)) {
+            continue;
+        }
+        neighbors.entry(city1).or_default().push(city2);
+        neighbors.entry(city2).or_default().push(city1);
+    }
+    if let Some(city) = neighbors.iter().find(|(_, neighbor)| neighbor.len() > 2).map(|(&city, _)| city) {
+        panic!("Invalid fixed edges.\nReason: City {} has more than two fixed edges.", city);
+    }
+    let mut endpoints: Vec<usize> = neighbors.iter().filter(|(_, neighbor)| neighbor.len() == 1).map(|(&city, _)| city).collect();
+    endpoints.sort();
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut chains: Vec<Vec<usize>> = Vec::new();
+    for start in endpoints {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut chain = vec![start];
+        visited.insert(start);
+        let (mut previous, mut current) = (start, neighbors[&start][0]);
+        loop {
+            chain.push(current);
+            visited.insert(current);
+            match neighbors[&current].iter().find(|&&next| next != previous) {
+                Some(&next) => (previous, current) = (current, next),
+                None => break,
+            }
+        }
+        chains.push(chain);
+    }
+    if visited.len() != neighbors.len() {
+        panic!("Invalid fixed edges.\nReason: Fixed edges form a cycle.");
+    }
+    chains
+}
+
+fn repair_solution(solution: Vec<usize>, chains: &[Vec<usize>]) -> Vec<usize> {
+    if chains.is_empty() {
+        return solution;
+    }
+    let mut chain_of: HashMap<usize, usize> = HashMap::new();
+    for (index, chain) in chains.iter().enumerate() {
+        for &city in chain {
+            chain_of.insert(city, index);
+        }
+    }
+    let mut emitted = vec![false; chains.len()];
+    let mut repaired: Vec<usize> = Vec::with_capacity(solution.len());
+    for &city in &solution {
+        match chain_of.get(&city) {
+            None => repaired.push(city),
+            Some(&index) if !emitted[index] => {
+                emitted[index] = true;
+                if chains[index].last() == Some(&city) {
+                    repaired.extend(chains[index].iter().rev());
+                } else {
+                    repaired.extend(&chains[index]);
+                }
+            },
+            Some(_) => {},
+        }
+    }
+    repaired
+}
+
+// The chains the search repairs tours to; with constraint_handling = Penalty broken fixed edges are
+// penalized instead, so there is nothing to repair.
+fn repair_chains(config: &ConfigKind) -> Vec<Vec<usize>> {
+    match config.constraint_handling {
+        ConstraintHandling::Repair => build_chains(&config.fixed_edges),
+        ConstraintHandling::Penalty => Vec::new(),
+    }
+}
+
+// Repairs a tour to every constraint that constraint_handling = Repair covers: the fixed edges, then
+// the time windows.
+fn repair_tour(solution: Vec<usize>, chains: &[Vec<usize>], distance: &DistanceMatrix, config: &ConfigKind) -> Vec<usize> {
+    let solution = repair_solution(solution, chains);
+    if config.constraint_handling == ConstraintHandling::Repair && !config.time_windows.is_empty() {
+        repair_windows(solution, distance, config)
+    } else {
+        solution
+    }
+}
+
+// One pass along the route from the depot: a city reached after its window closes moves ahead of the
+// cities before it whose windows close later, which is where an earliest-deadline-first order would
+// put it. Cities with fixed edges stay in place so their chains hold. This removes most lateness that
+// comes from visiting cities in the wrong order, but a tour can stay late, so lateness is still
+// penalized.
+fn repair_windows(solution: Vec<usize>, distance: &DistanceMatrix, config: &ConfigKind) -> Vec<usize> {
+    let time_windows = &config.time_windows;
+    let city_amount = solution.len();
+    let depot_position = solution.iter().position(|&city| city == 0).unwrap();
+    let mut route: Vec<usize> = solution[depot_position..].iter().chain(&solution[..depot_position]).copied().collect();
+    let mut chained = vec![false; city_amount];
+    for &(city1, city2) in &config.fixed_edges {
+        chained[city1] = true;
+        chained[city2] = true;
+    }
+    let mut departure_times: Vec<f64> = vec![time_windows[0].0; city_amount];
+    let departure = |route: &[usize], departure_times: &[f64], position: usize| {
+        let arrival = departure_times[position - 1] + distance.get(route[position - 1], route[position]);
+        arrival.max(time_windows[route[position]].0)
+    };
+    for position in 1..city_amount {
+        let city = route[position];
+        let arrival = departure_times[position - 1] + distance.get(route[position - 1], city);
+        let mut target = position;
+        if arrival > time_windows[city].1 && !chained[city] {
+            while target > 1 && !chained[route[target - 1]] && time_windows[route[target - 1]].1 > time_windows[city].1 {
+                target -= 1;
+            }
+            route[target..=position].rotate_right(1);
+        }
+        for moved_position in target..=position {
+            departure_times[moved_position] = departure(&route, &departure_times, moved_position);
+        }
+    }
+    route
+}
+
+fn validate_tour(tour: &[usize], city_amount: usize) -> Result<(), TourError> {
+    if tour.len() != city_amount {
+        return Err(TourError::WrongLength { expected: city_amount, found: tour.len() });
+    }
+    let mut visited = vec![false; city_amount];
+    for &city in tour {
+        if city >= city_amount {
+            return Err(TourError::OutOfRange(city));
+        }
+        if visited[city] {
+            return Err(TourError::Duplicate(city));
+        }
+        visited[city] = true;
+    }
+    Ok(())
+}
+
+// TSPLIB tour files (.opt.tour) list 1-based cities after TOUR_SECTION, terminated by -1 or EOF.
+fn read_tsplib_tour(tour_content: &str) -> Option<Vec<usize>> {
+    let (_, tour_section) = tour_content.split_once("TOUR_SECTION")?;
+    let tour = tour_section
+        .split_whitespace()
+        .take_while(|city| *city != "-1" && *city != "EOF")
+        .map(|city| match city.parse::<usize>() {
+            Ok(city) if city >= 1 => city - 1,
+            _ => panic!("Invalid tour file.\nReason: {} is not a 1-based city in TOUR_SECTION.", city),
+        })
+        .collect();
+    Some(tour)
+}
+
+fn read_tour(tour_path: &str) -> Vec<usize> {
+    let tour_content = fs::read_to_string(tour_path).expect("Fail read tour file.");
+    if let Some(tour) = read_tsplib_tour(&tour_content) {
+        return tour;
+    }
+    let tour_line = tour_content
+        .lines()
+        .find_map(|line| line.strip_prefix("Best solution:"))
+        .unwrap_or(&tour_content);
+    tour_line
+        .split_whitespace()
+        .map(|city| city.parse::<usize>().expect("Invalid tour file."))
+        .collect()
+}
+
+fn read_result_length(result_path: &str) -> f64 {
+    let result_content = fs::read_to_string(result_path).expect("Fail read result file.");
+    result_content
+        .lines()
+        .find_map(|line| line.strip_prefix("Best solution length:"))
+        .and_then(|length| length.split_whitespace().next())
+        .and_then(|length| length.parse::<f64>().ok())
+        .expect("Invalid result file.")
+}
+
+fn tour_edges(solution: &[usize]) -> HashSet<(usize, usize)> {
+    let city_amount = solution.len();
+    (0..city_amount)
+        .map(|i| {
+            let (city1, city2) = (solution[i], solution[(i + 1) % city_amount]);
+            (city1.min(city2), city1.max(city2))
+        })
+        .collect()
+}
+
+fn format_diff(old_path: &str, new_path: &str) -> String {
+    let (old_solution, new_solution) = (read_tour(old_path), read_tour(new_path));
+    let (old_length, new_length) = (read_result_length(old_path), read_result_length(new_path));
+    if old_solution.len() != new_solution.len() {
+        panic!("Invalid result file.\nReason: The solutions have {} and {} cities.", old_solution.len(), new_solution.len());
+    }
+    let old_edges = tour_edges(&old_solution);
+    let differing_edges = tour_edges(&new_solution).difference(&old_edges).count();
+    let mut diff_message = String::new();
+    diff_message.push_str(&format!("Length delta:{}\n", new_length - old_length));
+    diff_message.push_str(&format!("Length change:{}%\n", (new_length - old_length) / old_length * 100.0));
+    diff_message.push_str(&format!("Differing edges:{}/{}\n", differing_edges, new_solution.len()));
+    diff_message
+}
+
+fn diff_results(old_path: &str, new_path: &str) {
+    print!("{}", format_diff(old_path, new_path));
+}
+
+fn read_focus(focus_path: &str, city_amount: usize) -> Vec<usize> {
+    let mut focus_cities = read_tour(focus_path);
+    focus_cities.sort_unstable();
+    focus_cities.dedup();
+    if let Some(&city) = focus_cities.iter().find(|&&city| city >= city_amount) {
+        panic!("Invalid focus file.\nReason: City {} does not exist.", city);
+    }
+    if focus_cities.len() < 2 {
+        panic!("Invalid focus file.\nReason: At least two focus cities are required.");
+    }
+    focus_cities
+}
+
+// Reads one earliest,latest row per city in input order. The depot's row gives the departure time
+// and the latest return.
+fn read_time_windows(windows_path: &str, city_amount: usize) -> Vec<(f64, f64)> {
+    let windows_content = fs::read_to_string(windows_path).expect("Fail read windows file.");
+    let mut time_windows: Vec<(f64, f64)> = Vec::new();
+    for (index, line) in windows_content.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).enumerate() {
+        let window = match line.split(',').map(|col| col.trim().parse::<f64>()).collect::<Vec<_>>()[..] {
+            [Ok(earliest), Ok(latest)] => (earliest, latest),
+            _ => panic!("Invalid windows file.\nReason: Row {} does not have the form earliest,latest.", index + 1),
+        };
+        if !window.0.is_finite() || window.0 < 0.0 || window.1.is_nan() || window.1 < window.0 {
+            panic!("Invalid windows file.\nReason: Row {} needs 0 <= earliest <= latest.", index + 1);
+        }
+        time_windows.push(window);
+    }
+    if time_windows.len() != city_amount {
+        panic!("Invalid windows file.\nReason: {} windows were given for {} cities.", time_windows.len(), city_amount);
+    }
+    time_windows
+}
+
+// Reads rows written by --population-out. The leading length column is optional and ignored, since
+// lengths are recomputed for the current instance and configuration.
+fn read_population(population_path: &str, city_amount: usize) -> Vec<Vec<usize>> {
+    let population_content = fs::read_to_string(population_path).expect("Fail read population file.");
+    let mut population: Vec<Vec<usize>> = Vec::new();
+    for (index, line) in population_content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.split(',').map(|value| value.trim()).collect();
+        let tour_values = match values.len() {
+            length if length == city_amount => &values[..],
+            length if length == city_amount + 1 => &values[1..],
+            length => panic!("Invalid population file.\nReason: Row {} has {} values, expected {} cities with an optional length.", index + 1, length, city_amount),
+        };
+        let solution: Vec<usize> = tour_values.iter().map(|city| city.parse::<usize>().expect("Invalid population file.")).collect();
+        if let Err(e) = validate_tour(&solution, city_amount) {
+            panic!("Invalid population file.\nReason: Row {}: {}", index + 1, e);
+        }
+        population.push(solution);
+    }
+    population
+}
+
+fn verify_tour(cities: &[Vec<f64>], tour_path: &str, metric: DistanceMetric, rounding: DistanceRounding) {
+    let tour = read_tour(tour_path);
+    match validate_tour(&tour, cities.len()) {
+        Ok(()) => {
+            let distance = calc_cities_distance(cities, metric, rounding);
+            println!("Valid tour:true");
+            println!("Tour length:{}", calc_path_length(&tour, &distance));
+        },
+        Err(e) => {
+            println!("Valid tour:false");
+            println!("Reason:{}", e);
+        },
+    }
+}
+
+fn calc_path_length(solution: &[usize], distance: &DistanceMatrix) -> f64 {
+    let city_amount = solution.len();
+    (0..city_amount)
+        .map(|i| distance.get(solution[i], solution[(i + 1) % city_amount]))
+        .sum()
+}
+
+fn count_turns(solution: &[usize], cities: &[Vec<f64>], turn_angle: f64) -> usize {
+    let city_amount = solution.len();
+    let mut turns = 0;
+    for i in 0..city_amount {
+        let previous = &cities[solution[(i + city_amount - 1) % city_amount]];
+        let current = &cities[solution[i]];
+        let next = &cities[solution[(i + 1) % city_amount]];
+        let (mut dot, mut norm1, mut norm2) = (0.0, 0.0, 0.0);
+        for dimension in 0..current.len() {
+            let direction1 = current[dimension] - previous[dimension];
+            let direction2 = next[dimension] - current[dimension];
+            dot += direction1 * direction2;
+            norm1 += direction1 * direction1;
+            norm2 += direction2 * direction2;
+        }
+        if norm1 == 0.0 || norm2 == 0.0 {
+            continue;
+        }
+        let angle = (dot / (norm1.sqrt() * norm2.sqrt())).clamp(-1.0, 1.0).acos().to_degrees();
+        if angle > turn_angle {
+            turns += 1;
+        }
+    }
+    turns
+}
+
+fn compare_length(length1: f64, length2: f64, config: &ConfigKind) -> Ordering {
+    match config.optimize {
+        OptimizeDirection::Min => length1.partial_cmp(&length2).unwrap(),
+        OptimizeDirection::Max => length2.partial_cmp(&length1).unwrap(),
+    }
+}
+
+fn is_better(length1: f64, length2: f64, config: &ConfigKind) -> bool {
+    compare_length(length1, length2, config) == Ordering::Less
+}
+
+// Like is_better, but the gain must exceed improvement_epsilon relative to the old length, so
+// rounding noise between equally long tours is not mistaken for progress.
+fn is_improvement(new_length: f64, old_length: f64, config: &ConfigKind) -> bool {
+    is_better(new_length, old_length, config) && (old_length.is_infinite() || (old_length - new_length).abs() > config.improvement_epsilon * old_length.abs())
+}
+
+// Standard ABC fitness transform of the objective f: 1 / (1 + f) for f >= 0 and 1 + |f| for f < 0.
+// Higher is fitter. With optimize = Max the objective is the negated cost, so longer tours get
+// fitness above 1. Selection uses fitness; reporting and improvement checks keep the raw cost.
+fn fitness(cost: f64, config: &ConfigKind) -> f64 {
+    let objective = match config.optimize {
+        OptimizeDirection::Min => cost,
+        OptimizeDirection::Max => -cost,
+    };
+    if objective >= 0.0 {
+        1.0 / (1.0 + objective)
+    } else {
+        1.0 + objective.abs()
+    }
+}
+
+// Drives the tour from city 0, the depot, leaving at its earliest time with travel time equal to
+// distance. Arriving early waits for the window to open; arriving late adds the delay to the
+// lateness. Returns the total lateness and the number of late arrivals, including the return.
+fn calc_window_lateness(solution: &[usize], distance: &DistanceMatrix, time_windows: &[(f64, f64)]) -> (f64, usize) {
+    let city_amount = solution.len();
+    let depot_position = solution.iter().position(|&city| city == 0).unwrap();
+    let mut time = time_windows[0].0;
+    let (mut lateness, mut late_arrivals) = (0.0, 0);
+    for step in 1..=city_amount {
+        let previous = solution[(depot_position + step - 1) % city_amount];
+        let current = solution[(depot_position + step) % city_amount];
+        time += distance.get(previous, current);
+        let (earliest, latest) = time_windows[current];
+        if step < city_amount {
+            time = time.max(earliest);
+        }
+        if time > latest {
+            lateness += time - latest;
+            late_arrivals += 1;
+        }
+    }
+    (lateness, late_arrivals)
+}
+
+fn calc_solution_cost(solution: &[usize], distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> f64 {
+    let mut cost = calc_path_length(solution, distance);
+    if let Some(turn_angle) = config.turn_angle.filter(|_| config.turn_penalty > 0.0) {
+        cost += config.turn_penalty * count_turns(solution, cities, turn_angle) as f64;
+    }
+    if !config.time_windows.is_empty() {
+        cost += config.window_penalty * calc_window_lateness(solution, distance, &config.time_windows).0;
+    }
+    if config.constraint_handling == ConstraintHandling::Penalty && !config.fixed_edges.is_empty() {
+        cost += config.fixed_edge_penalty * count_missing_fixed_edges(solution, &config.fixed_edges) as f64;
+    }
+    cost
+}
+
+fn count_missing_fixed_edges(solution: &[usize], fixed_edges: &[(usize, usize)]) -> usize {
+    let city_amount = solution.len();
+    let positions = invert_solution(solution);
+    fixed_edges
+        .iter()
+        .filter(|&&(city1, city2)| {
+            let gap = positions[city1].abs_diff(positions[city2]);
+            gap != 1 && gap != city_amount - 1
+        })
+        .count()
+}
+
+// Lloyd's k-means on the city coordinates, returning the cities of each non-empty cluster together
+// with its centroid. Centroids start at distinct random cities and the loop stops once no city
+// changes cluster or after KMEANS_ITERATIONS rounds.
+fn k_means(cities: &[Vec<f64>], cluster_count: usize, rng: &mut impl Rng) -> Vec<(Vec<f64>, Vec<usize>)> {
+    let cluster_count = cluster_count.min(cities.len());
+    let mut centroids: Vec<Vec<f64>> = index::sample(rng, cities.len(), cluster_count).into_iter().map(|city| cities[city].clone()).collect();
+    let mut assignment: Vec<usize> = vec![usize::MAX; cities.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (city, coordinates) in cities.iter().enumerate() {
+            let nearest = (0..cluster_count)
+                .min_by(|&cluster1, &cluster2| euclidean_distance(coordinates, &centroids[cluster1]).total_cmp(&euclidean_distance(coordinates, &centroids[cluster2])))
+                .unwrap();
+            if assignment[city] != nearest {
+                assignment[city] = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f64>> = cities.iter().zip(&assignment).filter(|(_, &assigned)| assigned == cluster).map(|(coordinates, _)| coordinates).collect();
+            if members.is_empty() {
+                continue;
+            }
+            for dimension in 0..centroid.len() {
+                centroid[dimension] = members.iter().map(|coordinates| coordinates[dimension]).sum::<f64>() / members.len() as f64;
+            }
+        }
+    }
+    centroids
+        .into_iter()
+        .enumerate()
+        .map(|(cluster, centroid)| (centroid, (0..cities.len()).filter(|&city| assignment[city] == cluster).collect::<Vec<usize>>()))
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
+// Visits the clusters in nearest-neighbour order of their centroids from a random first cluster,
+// and the cities of each cluster in random order.
+fn cluster_solution(clusters: &[(Vec<f64>, Vec<usize>)], rng: &mut impl Rng) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..clusters.len()).collect();
+    let mut current = remaining.swap_remove(rng.gen_range(0..remaining.len()));
+    let mut solution: Vec<usize> = Vec::new();
+    loop {
+        let mut members = clusters[current].1.clone();
+        members.shuffle(rng);
+        solution.extend(members);
+        if remaining.is_empty() {
+            break;
+        }
+        let nearest = (0..remaining.len())
+            .min_by(|&position1, &position2| {
+                let distance1 = euclidean_distance(&clusters[current].0, &clusters[remaining[position1]].0);
+                let distance2 = euclidean_distance(&clusters[current].0, &clusters[remaining[position2]].0);
+                distance1.total_cmp(&distance2)
+            })
+            .unwrap();
+        current = remaining.swap_remove(nearest);
+    }
     solution
 }
 
-fn calc_path_length(solution: &Vec<usize>, distance: &Vec<Vec<f64>>) -> f64 {
-    let mut length = 0.0;
-    for i in 0..(solution.len()-1) {
-        length += distance[solution[i]][solution[i+1]];
+fn source_rng(config: &ConfigKind, index: usize) -> SmallRng {
+    match config.seed {
+        Some(seed) => SmallRng::seed_from_u64(seed.wrapping_add(index as u64)),
+        None => SmallRng::from_entropy(),
+    }
+}
+
+// Building a thread pool every phase costs more than it saves when there is little work to share.
+fn run_serially(city_amount: usize, config: &ConfigKind) -> bool {
+    config.force_serial || config.concurrent_count == 1 || config.colony_size.saturating_mul(city_amount) < SERIAL_WORK_THRESHOLD
+}
+
+// Builds the tours one after another, each the random candidate out of SPREAD_CANDIDATES whose
+// edges were used least often by the tours chosen so far, so the population covers more edges.
+fn spread_population(distance: &DistanceMatrix, source_amount: usize, chains: &[Vec<usize>], config: &ConfigKind, rng: &mut impl Rng) -> Vec<Vec<usize>> {
+    let mut edge_counts: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut solutions: Vec<Vec<usize>> = Vec::with_capacity(source_amount);
+    for _ in 0..source_amount {
+        let solution = (0..SPREAD_CANDIDATES)
+            .map(|_| repair_tour(random_tour(distance, rng), chains, distance, config))
+            .min_by_key(|candidate| tour_edges(candidate).iter().map(|edge| edge_counts.get(edge).copied().unwrap_or(0)).sum::<usize>())
+            .unwrap();
+        for edge in tour_edges(&solution) {
+            *edge_counts.entry(edge).or_insert(0) += 1;
+        }
+        solutions.push(solution);
     }
-    length += distance[solution[solution.len()-1]][solution[0]];
-    length
+    solutions
 }
 
-fn initialize_phase(distance: &Vec<Vec<f64>>, config: &ConfigKind) -> (Vec<Vec<usize>>, Vec<f64>) {
+fn initialize_phase(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind, seed_offset: u64) -> (Vec<Vec<usize>>, Vec<f64>) {
     let colony_size = config.colony_size;
     let concurrent_count = config.concurrent_count;
     let city_amount = distance.len();
+    let chains = repair_chains(config);
+    let (clusters, cluster_sources) = match config.init_method {
+        InitMethod::Random | InitMethod::Spread => (Vec::new(), 0),
+        InitMethod::Cluster => {
+            let cluster_count = config.cluster_count.unwrap_or(((city_amount as f64 / 2.0).sqrt().round() as usize).max(1));
+            let mut rng = match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(seed_offset)),
+                None => StdRng::from_entropy(),
+            };
+            (k_means(cities, cluster_count, &mut rng), (config.cluster_share * (colony_size / 2) as f64).round() as usize)
+        },
+    };
+    let generate_solution = |index: usize| {
+        let solution = match config.seed {
+            Some(seed) if index < cluster_sources => cluster_solution(&clusters, &mut StdRng::seed_from_u64(seed.wrapping_add(seed_offset + index as u64))),
+            Some(seed) => random_tour(distance, &mut StdRng::seed_from_u64(seed.wrapping_add(seed_offset + index as u64))),
+            None if index < cluster_sources => cluster_solution(&clusters, &mut rand::thread_rng()),
+            None => random_tour(distance, &mut rand::thread_rng()),
+        };
+        repair_tour(solution, &chains, distance, config)
+    };
+    if config.init_method == InitMethod::Spread {
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(seed_offset)),
+            None => StdRng::from_entropy(),
+        };
+        let solutions = spread_population(distance, colony_size / 2, &chains, config, &mut rng);
+        let solutions_length = solutions.iter().map(|solution| calc_solution_cost(solution, distance, cities, config)).collect();
+        return (solutions, solutions_length);
+    }
+    if run_serially(city_amount, config) {
+        let solutions: Vec<Vec<usize>> = (0..(colony_size / 2)).map(generate_solution).collect();
+        let solutions_length = solutions.iter().map(|solution| calc_solution_cost(solution, distance, cities, config)).collect();
+        return (solutions, solutions_length);
+    }
     let thread_pool = ThreadPoolBuilder::new().num_threads(concurrent_count).build().expect("Fail build thread pool.");
     let solutions: Vec<Vec<usize>> = thread_pool.install(
         || {
-            let solutions = (0..(colony_size / 2))
+            (0..(colony_size / 2))
                 .into_par_iter()
-                .map(|_| initialize_solution(city_amount))
-                .collect();
-            solutions
+                .map(generate_solution)
+                .collect()
         }
     );
     let solutions_length = thread_pool.install(
@@ -212,7 +2141,7 @@ fn initialize_phase(distance: &Vec<Vec<f64>>, config: &ConfigKind) -> (Vec<Vec<u
             let solutions_length: Vec<f64> = solutions
                 .clone()
                 .into_par_iter()
-                .map(|solution| calc_path_length(&solution, &distance))
+                .map(|solution| calc_solution_cost(&solution, distance, cities, config))
                 .collect();
             solutions_length
         }
@@ -220,63 +2149,28 @@ fn initialize_phase(distance: &Vec<Vec<f64>>, config: &ConfigKind) -> (Vec<Vec<u
     (solutions, solutions_length)
 }
 
-fn swap(solution: &Vec<usize>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
-    let mut neighbor = solution.clone();
-    let (city1, city2) = loop {
-        let (i, j) = (rng.gen_range(0..solution.len()), rng.gen_range(0..solution.len()));
-        if i == j {
-            continue;
-        } else {
-            break (i, j);
-        }
-    };
+fn swap(solution: &[usize], city1: usize, city2: usize) -> Vec<usize> {
+    let mut neighbor = solution.to_vec();
     neighbor.swap(city1, city2);
     neighbor
 }
 
-fn insert(solution: &Vec<usize>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
-    let mut neighbor = solution.clone();
-    let (mut city1, mut city2) = loop {
-        let (i, j) = (rng.gen_range(0..solution.len()), rng.gen_range(0..solution.len()));
-        if i == j {
-            continue;
-        } else {
-            break (i, j);
-        }
-    };
-    if city1 > city2 {
-        std::mem::swap(&mut city1, &mut city2);
-    }
+fn insert(solution: &[usize], city1: usize, city2: usize) -> Vec<usize> {
+    let mut neighbor = solution.to_vec();
     let moved_city = neighbor.remove(city2);
     neighbor.insert(city1 + 1, moved_city);
     neighbor
 }
 
-fn reverse (solution: &Vec<usize>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
-    let mut neighbor = solution.clone();
-    let (mut city1, mut city2) = loop {
-        let (i, j) = (rng.gen_range(0..solution.len()), rng.gen_range(0..solution.len()));
-        if i == j {
-            continue;
-        } else {
-            break (i, j);
-        }
-    };
-    if city1 > city2 {
-        std::mem::swap(&mut city1, &mut city2);
-    }
+fn reverse (solution: &[usize], city1: usize, city2: usize) -> Vec<usize> {
+    let mut neighbor = solution.to_vec();
     neighbor[city1..=city2].reverse();
     neighbor
 }
 
-fn partial_shuffle (solution: &Vec<usize>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
-    let mut neighbor = solution.clone();
+fn pick_positions(city_amount: usize, rng: &mut SmallRng) -> (usize, usize) {
     let (mut city1, mut city2) = loop {
-        let (i, j) = (rng.gen_range(0..solution.len()), rng.gen_range(0..solution.len()));
+        let (i, j) = (rng.gen_range(0..city_amount), rng.gen_range(0..city_amount));
         if i == j {
             continue;
         } else {
@@ -286,73 +2180,222 @@ fn partial_shuffle (solution: &Vec<usize>) -> Vec<usize> {
     if city1 > city2 {
         std::mem::swap(&mut city1, &mut city2);
     }
-    let partial = &mut neighbor[city1..=city2];
-    partial.shuffle(&mut rng);
-    neighbor
+    (city1, city2)
 }
 
-fn employed_bee(solution: &Vec<usize>, distance: &Vec<Vec<f64>>, config: &ConfigKind) -> Vec<usize> {
-    let candidate_amount = config.candidate_amount;
-    let generation_method = config.generation_method;
+// Two distinct positions of focus cities in the solution, in ascending order.
+fn pick_focus_positions(solution: &[usize], focus_cities: &[usize], rng: &mut SmallRng) -> (usize, usize) {
+    let mut focus_positions: Vec<usize> = solution.iter().enumerate().filter(|(_, city)| focus_cities.contains(city)).map(|(position, _)| position).collect();
+    focus_positions.shuffle(rng);
+    (focus_positions[0].min(focus_positions[1]), focus_positions[0].max(focus_positions[1]))
+}
+
+// Length change of reversing solution[city1..=city2]. Only the two boundary edges change, so this is
+// O(1); it relies on the distance matrix being symmetric. An asymmetric --matrix would also flip
+// every edge inside the segment, so employed_bee falls back to a full evaluation for it.
+fn reverse_delta(solution: &[usize], city1: usize, city2: usize, distance: &DistanceMatrix) -> f64 {
+    let city_amount = solution.len();
+    if city2 - city1 + 1 >= city_amount {
+        return 0.0;
+    }
+    let before = solution[(city1 + city_amount - 1) % city_amount];
+    let after = solution[(city2 + 1) % city_amount];
+    let (first, last) = (solution[city1], solution[city2]);
+    distance.get(before, last) + distance.get(first, after) - distance.get(before, first) - distance.get(last, after)
+}
+
+fn partial_shuffle (solution: &[usize], city1: usize, city2: usize, rng: &mut SmallRng) -> Vec<usize> {
+    let mut neighbor = solution.to_vec();
+    let partial = &mut neighbor[city1..=city2];
+    partial.shuffle(rng);
+    neighbor
+}
+
+// Candidates per employed bee in a given iteration, moving from candidate_amount to candidate_amount_end
+// (tournament_size by default) over max_iterations, either linearly or geometrically.
+fn scheduled_candidate_amount(iteration: usize, config: &ConfigKind) -> usize {
+    let start = config.candidate_amount as f64;
+    let end = config.candidate_amount_end.unwrap_or(config.tournament_size) as f64;
+    let progress = ((iteration - 1) as f64 / (config.max_iterations.max(2) - 1) as f64).min(1.0);
+    let candidate_amount = match config.candidate_schedule {
+        CandidateSchedule::Fixed => start,
+        CandidateSchedule::Linear => start + (end - start) * progress,
+        CandidateSchedule::Exp => start * (end / start).powf(progress),
+    };
+    candidate_amount.round() as usize
+}
+
+// Also returns how many tours were evaluated: every candidate, plus the full evaluation of the source
+// that the delta path and require_improvement need.
+fn employed_bee(solution: &[usize], elite_solution: &[usize], focus_probability: f64, rng: &mut SmallRng, distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> (Vec<usize>, usize) {
+    let candidate_amount = config.candidate_amount;
+    let generation_method = config.generation_method;
     let mut candidate_solution: Vec<Vec<usize>> = Vec::new();
+    let mut reverse_segments: Vec<(usize, usize)> = Vec::new();
+    let mut elite_candidates = 0;
     for _ in 0..candidate_amount {
+        let base_solution = if config.elite_mutation_rate > 0.0 && rng.gen::<f64>() < config.elite_mutation_rate {
+            elite_candidates += 1;
+            elite_solution
+        } else {
+            solution
+        };
+        let (city1, city2) = if focus_probability > 0.0 && rng.gen::<f64>() < focus_probability {
+            pick_focus_positions(base_solution, &config.focus_cities, rng)
+        } else {
+            pick_positions(base_solution.len(), rng)
+        };
         match generation_method {
             GenerationMethod::None => panic!("Unknown error."),
             GenerationMethod::Swap => {
-                candidate_solution.push(swap(solution));
+                candidate_solution.push(swap(base_solution, city1, city2));
             },
             GenerationMethod::Insert => {
-                candidate_solution.push(insert(solution));
+                candidate_solution.push(insert(base_solution, city1, city2));
             },
             GenerationMethod::Reverse => {
-                candidate_solution.push(reverse(solution));
+                candidate_solution.push(reverse(base_solution, city1, city2));
+                reverse_segments.push((city1, city2));
             },
             GenerationMethod::PartialShuffle => {
-                candidate_solution.push(partial_shuffle(solution));
+                candidate_solution.push(partial_shuffle(base_solution, city1, city2, rng));
             },
         }
     }
-    onlooker_bee(&candidate_solution, &distance)
+    let chains = repair_chains(config);
+    let candidate_solution: Vec<Vec<usize>> = candidate_solution
+        .into_iter()
+        .map(|candidate| repair_tour(candidate, &chains, distance, config))
+        .collect();
+    // Fixed edges, repaired or penalized, and turn penalties are not local to the reversed segment, so the delta is only used without them.
+    // Elite candidates do not derive from this source, so their segments say nothing about its length.
+    let use_delta = !reverse_segments.is_empty() && elite_candidates == 0 && config.fixed_edges.is_empty() && config.turn_angle.is_none() && config.time_windows.is_empty() && distance.is_symmetric();
+    let mut evaluations = candidate_amount;
+    let candidate_length: Vec<f64> = if use_delta {
+        evaluations += 1;
+        let solution_length = calc_path_length(solution, distance);
+        reverse_segments
+            .iter()
+            .map(|&(city1, city2)| solution_length + reverse_delta(solution, city1, city2, distance))
+            .collect()
+    } else {
+        candidate_solution
+            .iter()
+            .map(|candidate| calc_solution_cost(candidate, distance, cities, config))
+            .collect()
+    };
+    let mut selected_number = select_candidate(&candidate_length, rng, config);
+    if config.require_improvement {
+        evaluations += 1;
+        let solution_length = calc_solution_cost(solution, distance, cities, config);
+        if !is_improvement(candidate_length[selected_number], solution_length, config) {
+            let best_number = (0..candidate_amount)
+                .min_by(|&number1, &number2| compare_length(candidate_length[number1], candidate_length[number2], config))
+                .unwrap();
+            if !is_improvement(candidate_length[best_number], solution_length, config) {
+                return (solution.to_vec(), evaluations);
+            }
+            selected_number = best_number;
+        }
+    }
+    (candidate_solution[selected_number].clone(), evaluations)
+}
+
+// Runs onlooker_count independent onlooker selections and keeps the best candidate they picked.
+fn select_candidate(candidate_length: &[f64], rng: &mut SmallRng, config: &ConfigKind) -> usize {
+    (0..config.onlooker_count)
+        .map(|_| onlooker_bee(candidate_length, rng, config))
+        .reduce(|number1, number2| if is_better(candidate_length[number2], candidate_length[number1], config) { number2 } else { number1 })
+        .unwrap()
+}
+
+// The candidate that won most often. Ties in the win count go to the better tour, then to the lower
+// index, so no position is favoured over a shorter tour.
+fn most_selected(count: &[usize], candidate_length: &[f64], config: &ConfigKind) -> usize {
+    let max_count = *count.iter().max().unwrap();
+    (0..count.len())
+        .filter(|&number| count[number] == max_count)
+        .min_by(|&number1, &number2| compare_length(candidate_length[number1], candidate_length[number2], config).then(number1.cmp(&number2)))
+        .unwrap()
 }
 
-fn onlooker_bee(candidate_solution: &Vec<Vec<usize>>, distance: &Vec<Vec<f64>>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
-    let candidate_amount = candidate_solution.len();
+// Runs candidate_amount tournaments and picks the candidate that won most often.
+fn onlooker_bee(candidate_length: &[f64], rng: &mut SmallRng, config: &ConfigKind) -> usize {
+    let candidate_amount = candidate_length.len();
+    let tournament_size = config.tournament_size;
+    let candidate_fitness: Vec<f64> = candidate_length.iter().map(|&length| fitness(length, config)).collect();
     let mut selected: Vec<usize> = Vec::new();
     while selected.len() < candidate_amount {
-        let selected_number1 = rng.gen_range(0..candidate_amount);
-        let selected_number2 = rng.gen_range(0..candidate_amount);
-        if selected_number1 == selected_number2 {
-            continue;
-        }
-        let selected_candidate1 = &candidate_solution[selected_number1];
-        let selected_candidate2 = &candidate_solution[selected_number2];
-        if calc_path_length(selected_candidate1, &distance) > calc_path_length(selected_candidate2, &distance) {
-            selected.push(selected_number1);
-        } else {
-            selected.push(selected_number2);
-        }
+        let winner = index::sample(rng, candidate_amount, tournament_size)
+            .into_iter()
+            .min_by(|&number1, &number2| candidate_fitness[number2].partial_cmp(&candidate_fitness[number1]).unwrap())
+            .unwrap();
+        selected.push(winner);
     }
     let mut count: Vec<usize> = vec![0; candidate_amount];
     for &number in &selected {
         count[number] += 1;
     }
-    let max_count = *count.iter().max().unwrap();
-    let max_number = count.iter().position(|&count| count == max_count).unwrap();
-    candidate_solution[max_number].clone()
+    most_selected(&count, candidate_length, config)
+}
+
+// Picks the better of two food sources; NaN lengths lose and ties go to the lower index. The rule is
+// associative and commutative, so any reduction order yields the same index.
+fn better_index(index1: usize, index2: usize, solutions_length: &[f64], config: &ConfigKind) -> usize {
+    let (length1, length2) = (solutions_length[index1], solutions_length[index2]);
+    match (length1.is_nan(), length2.is_nan()) {
+        (false, true) => index1,
+        (true, false) => index2,
+        (true, true) => index1.min(index2),
+        (false, false) => match compare_length(length1, length2, config) {
+            Ordering::Less => index1,
+            Ordering::Greater => index2,
+            Ordering::Equal => index1.min(index2),
+        },
+    }
+}
+
+fn find_best_index(solutions_length: &[f64], config: &ConfigKind) -> usize {
+    if solutions_length.len() < PARALLEL_ARGMIN_THRESHOLD {
+        return (0..solutions_length.len())
+            .reduce(|index1, index2| better_index(index1, index2, solutions_length, config))
+            .unwrap();
+    }
+    let thread_pool = ThreadPoolBuilder::new().num_threads(config.concurrent_count).build().expect("Fail build thread pool.");
+    thread_pool.install(
+        || {
+            (0..solutions_length.len())
+                .into_par_iter()
+                .reduce_with(|index1, index2| better_index(index1, index2, solutions_length, config))
+                .unwrap()
+        }
+    )
 }
 
-fn exploration_phase(solutions: &Vec<Vec<usize>>, distance: &Vec<Vec<f64>>, config: &ConfigKind) -> (Vec<Vec<usize>>, Vec<f64>) {
+// The evaluation count covers the employed bees and the evaluation of every new tour.
+fn exploration_phase(solutions: &[Vec<usize>], elite_solution: &[usize], focus_probability: f64, rngs: &mut [SmallRng], distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> (Vec<Vec<usize>>, Vec<f64>, usize) {
     let concurrent_count = config.concurrent_count;
+    // Without par_chunk_size rayon splits adaptively; a fixed minimum keeps each task at least that many food sources.
+    let min_len = config.par_chunk_size.unwrap_or(1);
+    if run_serially(distance.len(), config) {
+        let (new_solutions, evaluations): (Vec<Vec<usize>>, Vec<usize>) = solutions
+            .iter()
+            .zip(rngs.iter_mut())
+            .map(|(solution, rng)| employed_bee(solution, elite_solution, focus_probability, rng, distance, cities, config))
+            .unzip();
+        let new_solutions_length = new_solutions.iter().map(|solution| calc_solution_cost(solution, distance, cities, config)).collect();
+        return (new_solutions, new_solutions_length, evaluations.iter().sum::<usize>() + solutions.len());
+    }
     let thread_pool = ThreadPoolBuilder::new().num_threads(concurrent_count).build().expect("Fail build thread pool.");
-    let new_solutions = thread_pool.install(
+    let (new_solutions, evaluations) = thread_pool.install(
         || {
-            let new_solutions: Vec<Vec<usize>> = solutions
-                .clone()
-                .into_par_iter()
-                .map(|solution| employed_bee(&solution, distance, config))
-                .collect();
-            new_solutions
+            let (new_solutions, evaluations): (Vec<Vec<usize>>, Vec<usize>) = solutions
+                .par_iter()
+                .zip(rngs.par_iter_mut())
+                .with_min_len(min_len)
+                .map(|(solution, rng)| employed_bee(solution, elite_solution, focus_probability, rng, distance, cities, config))
+                .unzip();
+            (new_solutions, evaluations)
         }
     );
     let new_solutions_length = thread_pool.install(
@@ -360,80 +2403,2650 @@ fn exploration_phase(solutions: &Vec<Vec<usize>>, distance: &Vec<Vec<f64>>, conf
             let new_solutions_length: Vec<f64> = new_solutions
                 .clone()
                 .into_par_iter()
-                .map(|solution| calc_path_length(&solution, distance))
+                .with_min_len(min_len)
+                .map(|solution| calc_solution_cost(&solution, distance, cities, config))
                 .collect();
             new_solutions_length
         }
     );
-    (new_solutions, new_solutions_length)
+    (new_solutions, new_solutions_length, evaluations.iter().sum::<usize>() + solutions.len())
 }
 
-fn artificial_bee_colony(distance: &Vec<Vec<f64>>, config: &ConfigKind) -> (Vec<usize>, f64) {
-    let city_amount = distance.len();
+fn draw_progress(iteration: usize, max_iterations: usize, best_solution_length: f64, start_time: Instant) {
+    let elapsed = start_time.elapsed().as_secs_f64();
+    let remaining = elapsed / iteration as f64 * (max_iterations - iteration) as f64;
+    print!("\rIteration {}/{} | Best length {} | ETA {:.1}s  ", iteration, max_iterations, best_solution_length, remaining);
+    let _ = io::stdout().flush();
+}
+
+// report_distance measures the best tour the way the output reports it, for the anytime lengths.
+#[allow(clippy::too_many_arguments)]
+fn artificial_bee_colony(distance: &DistanceMatrix, report_distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind, initial_solutions: &[Vec<usize>], mut stream: Option<&mut StreamKind>, debug_invariants: bool, show_progress: bool) -> ResultKind {
     let colony_size = config.colony_size;
     let max_iterations= config.max_iterations;
     let max_unimproved = config.max_unimproved;
-    let improvement_threshold = config.improvement_threshold;
-    let (mut solutions, mut solutions_length) = initialize_phase(&distance, &config);
-    let mut best_solution: Vec<usize> = solutions[0].clone();
-    let mut best_solution_length = solutions_length[0];
+    let stopping_criteria = build_stopping_criteria(config);
+    let (mut solutions, mut solutions_length) = initialize_phase(distance, cities, config, 0);
+    let mut evaluations = colony_size / 2 + initial_solutions.len();
+    for (index, initial_solution) in initial_solutions.iter().enumerate() {
+        solutions[index] = repair_tour(initial_solution.clone(), &repair_chains(config), distance, config);
+        solutions_length[index] = calc_solution_cost(&solutions[index], distance, cities, config);
+    }
+    let initial_best_index = find_best_index(&solutions_length, config);
+    let mut best_solution: Vec<usize> = solutions[initial_best_index].clone();
+    let mut best_solution_length = solutions_length[initial_best_index];
+    let mut best_tour_length = calc_path_length(&best_solution, report_distance);
+    let initial_best_length = *solutions_length.iter().min_by(|&length1, &length2| compare_length(*length1, *length2, config)).unwrap();
     let mut unimproved_times: Vec<usize> = vec![0; colony_size / 2];
-    for _ in 0..max_iterations {
-        let (mut new_solutions, mut new_solutions_length) = exploration_phase(&solutions, &distance, &config);
-        for index in 0..(colony_size / 2) {
-            if new_solutions_length[index] < solutions_length[index] {
-                solutions[index] = new_solutions[index].clone();
-                solutions_length[index] = new_solutions_length[index];
-                unimproved_times[index] = 0;
-            } else {
-                unimproved_times[index] += 1;
-            }
-        }
+    let chains = repair_chains(config);
+    let mut rngs: Vec<SmallRng> = (0..(colony_size / 2)).map(|index| source_rng(config, index)).collect();
+    let mut stagnant_iterations = 0;
+    let mut restart_times = 0;
+    let mut stop_reason = StopReason::MaxIterations;
+    let start_time = Instant::now();
+    let mut last_draw = start_time;
+    let mut last_improvement = start_time;
+    let mut temperature = config.initial_temperature;
+    let mut history: Vec<IterationKind> = Vec::new();
+    for iteration in 1.. {
+        let iteration_start = Instant::now();
+        let focus_probability = if config.focus_cities.is_empty() {
+            0.0
+        } else {
+            let focus_iterations = config.focus_iterations.unwrap_or((max_iterations / 2).max(1));
+            1.0 - ((iteration - 1) as f64 / focus_iterations as f64).min(1.0)
+        };
+        let scheduled_config;
+        let iteration_config = match config.candidate_schedule {
+            CandidateSchedule::Fixed => config,
+            _ => {
+                scheduled_config = ConfigKind {
+                    candidate_amount: scheduled_candidate_amount(iteration, config),
+                    ..config.clone()
+                };
+                &scheduled_config
+            },
+        };
+        let (new_solutions, new_solutions_length, exploration_evaluations) = exploration_phase(&solutions, &best_solution, focus_probability, &mut rngs, distance, cities, iteration_config);
+        evaluations += exploration_evaluations;
+        let improved_sources = if config.replacement == ReplacementMethod::Generational {
+            generational_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, new_solutions, new_solutions_length, config)
+        } else {
+            steady_state_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, &new_solutions, &new_solutions_length, temperature, &mut rngs, config)
+        };
+        temperature *= config.cooling_rate;
+        // Strictly greater: a source survives max_unimproved failed iterations and is abandoned on the next one.
         for index in 0..(colony_size / 2) {
             if unimproved_times[index] > max_unimproved {
-                solutions[index] = initialize_solution(city_amount);
-                solutions_length[index] = calc_path_length(&solutions[index], &distance);
+                solutions[index] = repair_tour(random_tour(distance, &mut rngs[index]), &chains, distance, config);
+                solutions_length[index] = calc_solution_cost(&solutions[index], distance, cities, config);
                 unimproved_times[index] = 0;
+                evaluations += 1;
             }
         }
-        let best_index = solutions_length.iter().enumerate().min_by(|&(_, length1), &(_, length2)| length1.partial_cmp(length2).unwrap()).unwrap().0;
-        if solutions_length[best_index] < best_solution_length {
-            let improvement = (best_solution_length - solutions_length[best_index]) / best_solution_length;
+        if config.replace_worst {
+            let worst_index = (0..(colony_size / 2))
+                .max_by(|&index1, &index2| compare_length(solutions_length[index1], solutions_length[index2], config).then(index2.cmp(&index1)))
+                .unwrap();
+            solutions[worst_index] = repair_tour(random_tour(distance, &mut rngs[worst_index]), &chains, distance, config);
+            solutions_length[worst_index] = calc_solution_cost(&solutions[worst_index], distance, cities, config);
+            unimproved_times[worst_index] = 0;
+            evaluations += 1;
+        }
+        let best_index = find_best_index(&solutions_length, config);
+        let mut improvement = None;
+        if is_improvement(solutions_length[best_index], best_solution_length, config) {
+            improvement = Some(calc_improvement(solutions_length[best_index], best_solution_length, config));
             best_solution = solutions[best_index].clone();
             best_solution_length = solutions_length[best_index];
-            if improvement < improvement_threshold {
-                break;
+            best_tour_length = calc_path_length(&best_solution, report_distance);
+            stagnant_iterations = 0;
+            last_improvement = Instant::now();
+        } else {
+            stagnant_iterations += 1;
+        }
+        history.push(IterationKind {
+            iteration,
+            best_tour_length,
+            improved_sources,
+            cost_time: iteration_start.elapsed(),
+        });
+        if let Some(stream) = stream.as_deref_mut().filter(|stream| iteration % stream.interval == 0) {
+            let stream_message = format_stream(iteration, &best_solution, best_tour_length, &solutions, report_distance, improved_sources, config);
+            if let Err(e) = stream.file.write_all(stream_message.as_bytes()).and_then(|_| stream.file.flush()) {
+                panic!("Failed to write stream.\nReason: {}", e);
+            }
+        }
+        if show_progress && (last_draw.elapsed() >= PROGRESS_INTERVAL || iteration == max_iterations) {
+            draw_progress(iteration, max_iterations, best_solution_length, start_time);
+            last_draw = Instant::now();
+        }
+        let search_state = SearchStateKind {
+            iteration,
+            evaluations,
+            improvement,
+            since_improvement: last_improvement.elapsed(),
+        };
+        if let Some(fired_reason) = check_stopping_criteria(&stopping_criteria, &search_state) {
+            stop_reason = fired_reason;
+            break;
+        }
+        if config.global_restart_after.is_some_and(|global_restart_after| stagnant_iterations >= global_restart_after) {
+            restart_times += 1;
+            (solutions, solutions_length) = initialize_phase(distance, cities, config, (restart_times * colony_size / 2) as u64);
+            solutions[0] = best_solution.clone();
+            solutions_length[0] = best_solution_length;
+            unimproved_times = vec![0; colony_size / 2];
+            stagnant_iterations = 0;
+            evaluations += colony_size / 2;
+        }
+        if config.multistart > 1 && iteration % config.migration_interval == 0 {
+            migrate(&mut solutions, &mut solutions_length, &mut unimproved_times, config);
+        }
+        if debug_invariants {
+            check_invariants(&solutions, &solutions_length, &unimproved_times, distance, cities, config);
+        }
+    }
+    if show_progress {
+        println!();
+    }
+    ResultKind {
+        best_solution,
+        best_solution_length,
+        solutions,
+        initial_best_length,
+        evaluations,
+        stop_reason,
+        history,
+    }
+}
+
+// (μ+λ) selection: the best half of the old and new tours survives, old tours first on ties. Surviving
+// new tours count as improved; surviving old tours as another iteration without improvement.
+// Each source is replaced by its own candidate when the candidate improves on it; otherwise the
+// source counts a failed iteration, and under SimulatedAnnealing still moves to the worse candidate
+// with probability exp(-worsening / temperature). Returns the number of improved sources.
+#[allow(clippy::too_many_arguments)]
+fn steady_state_replacement(solutions: &mut [Vec<usize>], solutions_length: &mut [f64], unimproved_times: &mut [usize], new_solutions: &[Vec<usize>], new_solutions_length: &[f64], temperature: f64, rngs: &mut [SmallRng], config: &ConfigKind) -> usize {
+    let mut improved_sources = 0;
+    for index in 0..solutions.len() {
+        if is_improvement(new_solutions_length[index], solutions_length[index], config) {
+            solutions[index] = new_solutions[index].clone();
+            solutions_length[index] = new_solutions_length[index];
+            unimproved_times[index] = 0;
+            improved_sources += 1;
+        } else {
+            if config.acceptance == AcceptanceMethod::SimulatedAnnealing {
+                let worsening = (new_solutions_length[index] - solutions_length[index]).abs();
+                if rngs[index].gen::<f64>() < (-worsening / temperature).exp() {
+                    solutions[index] = new_solutions[index].clone();
+                    solutions_length[index] = new_solutions_length[index];
+                }
             }
+            unimproved_times[index] += 1;
+        }
+    }
+    improved_sources
+}
+
+fn generational_replacement(solutions: &mut Vec<Vec<usize>>, solutions_length: &mut Vec<f64>, unimproved_times: &mut Vec<usize>, new_solutions: Vec<Vec<usize>>, new_solutions_length: Vec<f64>, config: &ConfigKind) -> usize {
+    let source_amount = solutions.len();
+    let mut pool: Vec<(Vec<usize>, f64, Option<usize>)> = mem::take(solutions)
+        .into_iter()
+        .zip(solutions_length.iter().copied())
+        .zip(unimproved_times.iter().copied())
+        .map(|((solution, solution_length), unimproved)| (solution, solution_length, Some(unimproved)))
+        .chain(new_solutions.into_iter().zip(new_solutions_length).map(|(solution, solution_length)| (solution, solution_length, None)))
+        .collect();
+    pool.sort_by(|(_, length1, _), (_, length2, _)| compare_length(*length1, *length2, config));
+    pool.truncate(source_amount);
+    let improved_sources = pool.iter().filter(|(_, _, unimproved)| unimproved.is_none()).count();
+    *unimproved_times = pool.iter().map(|(_, _, unimproved)| unimproved.map_or(0, |unimproved| unimproved + 1)).collect();
+    *solutions_length = pool.iter().map(|(_, solution_length, _)| *solution_length).collect();
+    *solutions = pool.into_iter().map(|(solution, _, _)| solution).collect();
+    improved_sources
+}
+
+// Splits the food sources into config.multistart contiguous islands of near-equal size. On
+// migration each island's best tours replace the worst tours of the next island in a ring.
+fn migrate(solutions: &mut [Vec<usize>], solutions_length: &mut [f64], unimproved_times: &mut [usize], config: &ConfigKind) {
+    let source_amount = solutions.len();
+    let island_amount = config.multistart.min(source_amount);
+    let islands: Vec<Vec<usize>> = (0..island_amount)
+        .map(|island| {
+            let mut members: Vec<usize> = ((island * source_amount / island_amount)..((island + 1) * source_amount / island_amount)).collect();
+            members.sort_by(|&index1, &index2| compare_length(solutions_length[index1], solutions_length[index2], config).then(index1.cmp(&index2)));
+            members
+        })
+        .collect();
+    let emigrants: Vec<Vec<(Vec<usize>, f64)>> = islands
+        .iter()
+        .map(|members| members.iter().take(config.migration_count).map(|&index| (solutions[index].clone(), solutions_length[index])).collect())
+        .collect();
+    for (island, island_emigrants) in emigrants.into_iter().enumerate() {
+        let target = &islands[(island + 1) % island_amount];
+        for (&index, (solution, solution_length)) in target.iter().rev().zip(island_emigrants) {
+            solutions[index] = solution;
+            solutions_length[index] = solution_length;
+            unimproved_times[index] = 0;
+        }
+    }
+}
+
+// How much new_length improves on old_length, as a fraction of old_length or in length units
+// depending on improvement_mode; compared against improvement_threshold.
+fn calc_improvement(new_length: f64, old_length: f64, config: &ConfigKind) -> f64 {
+    let length_difference = (old_length - new_length).abs();
+    match config.improvement_mode {
+        ImprovementMode::Relative => length_difference / old_length,
+        ImprovementMode::Absolute => length_difference,
+    }
+}
+
+fn check_invariants(solutions: &[Vec<usize>], solutions_length: &[f64], unimproved_times: &[usize], distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) {
+    for (index, solution) in solutions.iter().enumerate() {
+        if let Err(e) = validate_tour(solution, distance.len()) {
+            panic!("Invariant violated.\nReason: Food source {} is not a valid tour. {}", index, e);
+        }
+        let solution_length = calc_solution_cost(solution, distance, cities, config);
+        if (solution_length - solutions_length[index]).abs() > INVARIANT_TOLERANCE * solution_length.abs().max(1.0) {
+            panic!("Invariant violated.\nReason: Food source {} has length {}, recomputed {}.", index, solutions_length[index], solution_length);
+        }
+        if unimproved_times[index] > config.max_unimproved {
+            panic!("Invariant violated.\nReason: Food source {} has been unimproved {} times, above max_unimproved.", index, unimproved_times[index]);
+        }
+    }
+}
+
+struct SearchStateKind {
+    iteration: usize,
+    evaluations: usize,
+    improvement: Option<f64>,
+    since_improvement: Duration,
+}
+
+trait StoppingCriterion {
+    fn should_stop(&self, search_state: &SearchStateKind) -> bool;
+    fn stop_reason(&self) -> StopReason;
+}
+
+struct ImprovementThresholdCriterion(f64);
+
+impl StoppingCriterion for ImprovementThresholdCriterion {
+    fn should_stop(&self, search_state: &SearchStateKind) -> bool {
+        search_state.improvement.is_some_and(|improvement| improvement < self.0)
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        StopReason::ImprovementThreshold
+    }
+}
+
+struct EvaluationBudgetCriterion(usize);
+
+impl StoppingCriterion for EvaluationBudgetCriterion {
+    fn should_stop(&self, search_state: &SearchStateKind) -> bool {
+        search_state.evaluations >= self.0
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        StopReason::EvaluationBudget
+    }
+}
+
+struct StagnantCriterion(Duration);
+
+impl StoppingCriterion for StagnantCriterion {
+    fn should_stop(&self, search_state: &SearchStateKind) -> bool {
+        search_state.since_improvement >= self.0
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        StopReason::Stagnant
+    }
+}
+
+struct MaxIterationsCriterion(usize);
+
+impl StoppingCriterion for MaxIterationsCriterion {
+    fn should_stop(&self, search_state: &SearchStateKind) -> bool {
+        search_state.iteration >= self.0
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        StopReason::MaxIterations
+    }
+}
+
+// All criteria are checked after every iteration and the search stops as soon as one fires. When
+// several fire in the same iteration, the one listed first here is reported.
+fn build_stopping_criteria(config: &ConfigKind) -> Vec<Box<dyn StoppingCriterion>> {
+    let mut stopping_criteria: Vec<Box<dyn StoppingCriterion>> = Vec::new();
+    // A zero threshold disables the criterion outright, so a rounding-level improvement can never
+    // stop the run.
+    if config.improvement_threshold > 0.0 {
+        stopping_criteria.push(Box::new(ImprovementThresholdCriterion(config.improvement_threshold)));
+    }
+    if let Some(max_evaluations) = config.max_evaluations {
+        stopping_criteria.push(Box::new(EvaluationBudgetCriterion(max_evaluations)));
+    }
+    if let Some(no_improve_secs) = config.no_improve_secs {
+        stopping_criteria.push(Box::new(StagnantCriterion(Duration::from_secs_f64(no_improve_secs))));
+    }
+    stopping_criteria.push(Box::new(MaxIterationsCriterion(config.max_iterations)));
+    stopping_criteria
+}
+
+fn check_stopping_criteria(stopping_criteria: &[Box<dyn StoppingCriterion>], search_state: &SearchStateKind) -> Option<StopReason> {
+    stopping_criteria.iter().find(|criterion| criterion.should_stop(search_state)).map(|criterion| criterion.stop_reason())
+}
+
+fn random_search(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind, evaluations: usize) -> Vec<usize> {
+    let city_amount = distance.len();
+    let chains = repair_chains(config);
+    let thread_pool = ThreadPoolBuilder::new().num_threads(config.concurrent_count).build().expect("Fail build thread pool.");
+    thread_pool.install(
+        || {
+            (0..evaluations)
+                .into_par_iter()
+                .map(|sample| {
+                    let solution = match config.seed {
+                        Some(seed) => seeded_solution(city_amount, seed, BASELINE_SEED_OFFSET + sample as u64),
+                        None => initialize_solution(city_amount, &mut rand::thread_rng()),
+                    };
+                    let solution = repair_tour(solution, &chains, distance, config);
+                    let solution_length = calc_solution_cost(&solution, distance, cities, config);
+                    (solution, solution_length)
+                })
+                .min_by(|(_, length1), (_, length2)| compare_length(*length1, *length2, config))
+                .unwrap()
+                .0
+        }
+    )
+}
+
+fn count_edge_frequency(solutions: &[Vec<usize>]) -> Vec<((usize, usize), usize)> {
+    let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+    for solution in solutions {
+        let city_amount = solution.len();
+        for i in 0..city_amount {
+            let (city1, city2) = (solution[i], solution[(i + 1) % city_amount]);
+            *edge_count.entry((city1.min(city2), city1.max(city2))).or_insert(0) += 1;
+        }
+    }
+    let mut edge_frequency: Vec<((usize, usize), usize)> = edge_count.into_iter().collect();
+    edge_frequency.sort_by(|(edge1, count1), (edge2, count2)| count2.cmp(count1).then(edge1.cmp(edge2)));
+    edge_frequency
+}
+
+fn calc_bounding_box(cities: &[Vec<f64>]) -> Vec<f64> {
+    (0..cities[0].len())
+        .map(|dimension| {
+            let values = cities.iter().map(|city| city[dimension]);
+            values.clone().fold(f64::NEG_INFINITY, f64::max) - values.fold(f64::INFINITY, f64::min)
+        })
+        .collect()
+}
+
+fn format_matrix(distance: &DistanceMatrix) -> String {
+    let mut matrix_message = String::new();
+    for city1 in 0..distance.len() {
+        let row_format: Vec<String> = (0..distance.len()).map(|city2| distance.get(city1, city2).to_string()).collect();
+        matrix_message.push_str(&row_format.join(","));
+        matrix_message.push('\n');
+    }
+    matrix_message
+}
+
+// Lengths are plain tour lengths in the reported units, measured on report_distance rather than taken
+// from the search costs. Diversity is the mean share of a food source's edges that are not in the best
+// tour: 0 means every source has converged to the best tour's edges.
+fn format_stream(iteration: usize, best_solution: &[usize], best_tour_length: f64, solutions: &[Vec<usize>], report_distance: &DistanceMatrix, improved_sources: usize, config: &ConfigKind) -> String {
+    let mean_length = solutions.iter().map(|solution| calc_path_length(solution, report_distance)).sum::<f64>() / solutions.len() as f64;
+    let best_edges = tour_edges(best_solution);
+    let diversity = solutions
+        .iter()
+        .map(|solution| tour_edges(solution).difference(&best_edges).count() as f64 / solution.len() as f64)
+        .sum::<f64>() / solutions.len() as f64;
+    let acceptance_rate = improved_sources as f64 / solutions.len() as f64;
+    format!(
+        "{{\"iter\":{},\"best\":{},\"mean\":{},\"diversity\":{},\"accept_rate\":{}}}\n",
+        iteration,
+        json_value(&(best_tour_length * config.distance_scale).to_string()),
+        json_value(&(mean_length * config.distance_scale).to_string()),
+        json_value(&diversity.to_string()),
+        json_value(&acceptance_rate.to_string())
+    )
+}
+
+fn format_history(history: &[IterationKind], config: &ConfigKind, iter_timing: bool) -> String {
+    let mut history_message = String::from("iteration,best_solution_length,improved_sources,acceptance_rate");
+    history_message.push_str(if iter_timing { ",cost_time_us\n" } else { "\n" });
+    for iteration in history {
+        let acceptance_rate = iteration.improved_sources as f64 / (config.colony_size / 2) as f64;
+        history_message.push_str(&format!("{},{},{},{}", iteration.iteration, iteration.best_tour_length * config.distance_scale, iteration.improved_sources, acceptance_rate));
+        if iter_timing {
+            history_message.push_str(&format!(",{}", iteration.cost_time.as_micros()));
+        }
+        history_message.push('\n');
+    }
+    history_message
+}
+
+// The best length after each checkpoint iteration; a run that stopped earlier keeps its final best.
+fn format_anytime(history: &[IterationKind], final_length: f64, checkpoints: &[f64], config: &ConfigKind, precision: Option<usize>) -> String {
+    let checkpoint_format: Vec<String> = checkpoints
+        .iter()
+        .map(|&checkpoint| {
+            let checkpoint_iteration = (checkpoint / 100.0 * config.max_iterations as f64).ceil() as usize;
+            let best_length = history
+                .iter()
+                .take_while(|iteration| iteration.iteration <= checkpoint_iteration)
+                .last()
+                .map_or(final_length, |iteration| iteration.best_tour_length);
+            format!("{}%={}", checkpoint, format_length(best_length, config, precision))
+        })
+        .collect();
+    checkpoint_format.join(" ")
+}
+
+fn format_gnuplot(solution: &[usize], cities: &[Vec<f64>]) -> String {
+    let mut gnuplot_message = String::new();
+    for &city in solution.iter().chain(solution.first()) {
+        let city_format: Vec<String> = cities[city].iter().map(|value| value.to_string()).collect();
+        gnuplot_message.push_str(&city_format.join(" "));
+        gnuplot_message.push('\n');
+    }
+    gnuplot_message
+}
+
+// One food source per row: its length followed by the tour's 0-based city indices.
+fn format_population(solutions: &[Vec<usize>], distance: &DistanceMatrix, config: &ConfigKind) -> String {
+    let mut population_message = String::new();
+    for solution in solutions {
+        let solution_format: Vec<String> = solution.iter().map(|city| city.to_string()).collect();
+        population_message.push_str(&format!("{},{}\n", calc_path_length(solution, distance) * config.distance_scale, solution_format.join(",")));
+    }
+    population_message
+}
+
+fn format_optimal_gap(best_solution: &[usize], best_solution_length: f64, optimal_tour: &[usize], report_distance: &DistanceMatrix, config: &ConfigKind, precision: Option<usize>) -> String {
+    let optimal_length = calc_path_length(optimal_tour, report_distance);
+    let shared_edges = tour_edges(best_solution).intersection(&tour_edges(optimal_tour)).count();
+    let mut gap_message = String::new();
+    gap_message.push_str(&format!("Optimal length:{}\n", format_length(optimal_length, config, precision)));
+    gap_message.push_str(&format!("Gap to optimal:{}\n", format_length(best_solution_length - optimal_length, config, precision)));
+    gap_message.push_str(&format!("Gap to optimal percent:{}%\n", (best_solution_length - optimal_length) / optimal_length * 100.0));
+    gap_message.push_str(&format!("Shared optimal edges:{}/{}\n", shared_edges, tour_edges(optimal_tour).len()));
+    gap_message
+}
+
+fn invert_solution(solution: &[usize]) -> Vec<usize> {
+    let mut inverse = vec![0; solution.len()];
+    for (position, &city) in solution.iter().enumerate() {
+        inverse[city] = position;
+    }
+    inverse
+}
+
+// Output paths such as results/run1.txt may point into directories that do not exist yet.
+fn create_parent_dir(output_path: &str) {
+    if let Some(parent_dir) = Path::new(output_path).parent().filter(|parent_dir| !parent_dir.as_os_str().is_empty()) {
+        if let Err(e) = fs::create_dir_all(parent_dir) {
+            panic!("Failed to create directory {}.\nReason: {}", parent_dir.display(), e);
         }
     }
-    (best_solution, best_solution_length)
 }
 
 fn write_result(output_path: String, output_message: String) {
-    let mut output_file = match OpenOptions::new().read(true).write(true).create(true).truncate(true).open(output_path) {
+    create_parent_dir(&output_path);
+    let mut output_file = match OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&output_path) {
         Ok(output_file) => output_file,
-        Err(_) => panic!("Failed to open or create file."),
+        Err(e) => panic!("Failed to open or create file {}.\nReason: {}", output_path, e),
     };
     if let Err(e) = output_file.write_all(output_message.as_bytes()) {
         panic!("Failed to write to file.\nReason: {}", e);
     }
 }
 
-fn main() {
-    let start_time = Instant::now();
-    let arguments = get_arguments();
-    let input_path = arguments.input.expect("Missing argument.");
-    let output_path = arguments.output.expect("Missing argument.");
-    let config_path = arguments.config.expect("Missing argument.");
-    let cities = read_xlsx(input_path);
-    let distance = calc_cities_distance(&cities);
-    let config = read_config(config_path);
-    validate_config(&config);
-    let (best_solution, best_solution_length) = artificial_bee_colony(&distance, &config);
+fn append_result(output_path: String, output_record: String) {
+    create_parent_dir(&output_path);
+    let mut output_file = match OpenOptions::new().append(true).create(true).open(&output_path) {
+        Ok(output_file) => output_file,
+        Err(e) => panic!("Failed to open or create file {}.\nReason: {}", output_path, e),
+    };
+    let is_new_file = output_file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true);
     let mut output_message = String::new();
+    if is_new_file {
+        output_message.push_str(RECORD_HEADER);
+    }
+    output_message.push_str(&output_record);
+    if let Err(e) = output_file.write_all(output_message.as_bytes()) {
+        panic!("Failed to write to file.\nReason: {}", e);
+    }
+}
+
+fn format_length(length: f64, config: &ConfigKind, precision: Option<usize>) -> String {
+    let scaled_length = length * config.distance_scale;
+    let length_format = match precision {
+        Some(precision) => format!("{:.*}", precision, scaled_length),
+        None => scaled_length.to_string(),
+    };
+    if config.distance_unit.is_empty() {
+        length_format
+    } else {
+        format!("{} {}", length_format, config.distance_unit)
+    }
+}
+
+fn format_record(config: &ConfigKind, best_solution: &[usize], best_solution_length: f64, cost_time: Duration) -> String {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
     let solution_format: Vec<String> = best_solution.iter().map(|city| city.to_string()).collect();
-    output_message.push_str(&format!("Best solution:{}\n", solution_format.join(" ")));
-    output_message.push_str(&format!("Best solution length:{}\n", best_solution_length));
-    output_message.push_str(&format!("Cost time:{:?}\n", start_time.elapsed()));
-    write_result(output_path, output_message);
+    format!(
+        "{},{},{},{},{},{},{},{},{:?},{},{},{}\n",
+        timestamp,
+        config.colony_size,
+        config.candidate_amount,
+        config.tournament_size,
+        config.max_unimproved,
+        config.max_iterations,
+        config.improvement_threshold,
+        config.concurrent_count,
+        config.generation_method,
+        best_solution_length * config.distance_scale,
+        cost_time.as_secs_f64(),
+        solution_format.join(" "),
+    )
+}
+
+fn format_csv(data: &[Vec<f64>]) -> String {
+    let mut csv_message = String::new();
+    for row in data {
+        let row_format: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+        csv_message.push_str(&row_format.join(","));
+        csv_message.push('\n');
+    }
+    csv_message
+}
+
+fn convert_input(input_path: String, convert_path: String, open_retries: usize) {
+    let cities = read_input(input_path, open_retries);
+    match file_extension(&convert_path).as_deref() {
+        Some("csv") => write_result(convert_path, format_csv(&cities)),
+        _ => panic!("Unsupported convert format."),
+    }
+}
+
+fn hash_file(file_path: &str) -> Option<String> {
+    let file_content = fs::read(file_path).ok()?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in file_content {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Some(format!("{:016x}", hash))
+}
+
+fn json_string(value: &str) -> String {
+    let mut json_message = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => json_message.push_str("\\\""),
+            '\\' => json_message.push_str("\\\\"),
+            '\n' => json_message.push_str("\\n"),
+            '\r' => json_message.push_str("\\r"),
+            '\t' => json_message.push_str("\\t"),
+            _ if (character as u32) < 0x20 => json_message.push_str(&format!("\\u{:04x}", character as u32)),
+            _ => json_message.push(character),
+        }
+    }
+    json_message.push('"');
+    json_message
+}
+
+fn json_value(value: &str) -> String {
+    match value {
+        "true" | "false" => value.to_string(),
+        _ if value.parse::<f64>().is_ok_and(|number| number.is_finite()) => value.to_string(),
+        _ => json_string(value),
+    }
+}
+
+// The arguments object holds the command-line inputs that change the problem being solved, each
+// file with its hash, and search_turn_penalty the turn_penalty after normalize_coords and
+// penalty_scale rescaled it for the search.
+fn format_meta(config: &ConfigKind, input_path: &str, input_hash: Option<String>, arguments: &ArgumentKind, search_turn_penalty: f64) -> String {
+    let config_format: Vec<String> = format_config(config)
+        .iter()
+        .map(|(key, value)| format!("    {}: {}", json_string(key), json_value(value)))
+        .collect();
+    let mut argument_format: Vec<String> = vec![
+        format!("    \"limit_cities\": {}", arguments.limit_cities.map_or("null".to_string(), |limit| limit.to_string())),
+        format!("    \"sample_cities\": {}", arguments.sample_cities),
+    ];
+    let argument_paths = [
+        ("matrix", &arguments.matrix),
+        ("edges", &arguments.edges),
+        ("windows", &arguments.windows),
+        ("focus", &arguments.focus),
+        ("seed_tour", &arguments.seed_tour),
+        ("population_in", &arguments.population_in),
+    ];
+    for (name, argument_path) in argument_paths {
+        let path_format = argument_path.as_deref().map_or("null".to_string(), json_string);
+        let hash_format = argument_path.as_deref().and_then(hash_file).map_or("null".to_string(), |hash| json_string(&hash));
+        argument_format.push(format!("    {}: {}", json_string(name), path_format));
+        argument_format.push(format!("    {}: {}", json_string(&format!("{}_hash", name)), hash_format));
+    }
+    let mut meta_message = String::from("{\n");
+    meta_message.push_str(&format!("  \"version\": {},\n", json_string(env!("CARGO_PKG_VERSION"))));
+    meta_message.push_str(&format!("  \"input\": {},\n", json_string(input_path)));
+    meta_message.push_str(&format!("  \"input_hash\": {},\n", input_hash.map_or("null".to_string(), |input_hash| json_string(&input_hash))));
+    meta_message.push_str(&format!("  \"threads\": {},\n", config.concurrent_count));
+    meta_message.push_str(&format!("  \"arguments\": {{\n{}\n  }},\n", argument_format.join(",\n")));
+    meta_message.push_str(&format!("  \"search_turn_penalty\": {},\n", json_value(&search_turn_penalty.to_string())));
+    meta_message.push_str(&format!("  \"config\": {{\n{}\n  }}\n", config_format.join(",\n")));
+    meta_message.push_str("}\n");
+    meta_message
+}
+
+fn autotune(input_path: String, output_path: Option<String>, config: &ConfigKind, open_retries: usize) {
+    let cities = if config.columns.is_empty() { read_input(input_path, open_retries) } else { read_mapped_input(input_path, &config.columns, open_retries).0 };
+    let (normalized_cities, search_config) = normalize_coordinates(&cities, config);
+    let distance = build_distance_matrix(&normalized_cities, &search_config);
+    let search_config = scale_penalties(search_config, config, &distance);
+    let report_distance = DistanceMatrix::Lazy { cities: cities.clone(), metric: config.distance_metric, rounding: config.distance_rounding };
+    let city_amount = cities.len();
+    let budget = config.max_evaluations.map_or(AUTOTUNE_EVALUATIONS, |max_evaluations| max_evaluations.min(AUTOTUNE_EVALUATIONS));
+    let suggested_size = ((2.0 * (city_amount as f64).sqrt()).ceil() as usize).next_multiple_of(2);
+    let mut colony_sizes: Vec<usize> = [suggested_size, suggested_size * 2, suggested_size * 4]
+        .iter()
+        .map(|&colony_size| colony_size.min(count_distinct_tours(city_amount, colony_size / 2) * 2))
+        .collect();
+    colony_sizes.dedup();
+    let mut best_trial: Option<(ConfigKind, f64)> = None;
+    for &colony_size in &colony_sizes {
+        for candidate_amount in [colony_size / 4, colony_size / 2] {
+            if candidate_amount < config.tournament_size {
+                continue;
+            }
+            for max_unimproved in [10, 50] {
+                let trial_config = ConfigKind {
+                    colony_size,
+                    candidate_amount,
+                    max_unimproved,
+                    max_iterations: budget,
+                    max_evaluations: Some(budget),
+                    ..search_config.clone()
+                };
+                let result = artificial_bee_colony(&distance, &report_distance, &cities, &trial_config, &[], None, false, false);
+                let best_length = calc_path_length(&result.best_solution, &report_distance);
+                println!("Trial:colony_size={} candidate_amount={} max_unimproved={} length={}", colony_size, candidate_amount, max_unimproved, format_length(best_length, config, None));
+                if best_trial.as_ref().is_none_or(|(_, best_trial_length)| is_better(result.best_solution_length, *best_trial_length, config)) {
+                    best_trial = Some((trial_config, result.best_solution_length));
+                }
+            }
+        }
+    }
+    let (best_config, _) = best_trial.expect("Invalid configuration.\nReason: tournament_size is too large for every colony size tried.");
+    let tuned_config = ConfigKind {
+        colony_size: best_config.colony_size,
+        candidate_amount: best_config.candidate_amount,
+        max_unimproved: best_config.max_unimproved,
+        ..config.clone()
+    };
+    let mut config_format = String::new();
+    for (key, value) in format_config(&tuned_config) {
+        config_format.push_str(&format!("{} = {}\n", key, value));
+    }
+    print!("{}", config_format);
+    if let Some(output_path) = output_path {
+        write_result(output_path, config_format);
+    }
+}
+
+// Builds the tour the first food source would start from, without any ABC iterations.
+fn construct_only(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> ResultKind {
+    let construct_config = ConfigKind {
+        colony_size: 2,
+        cluster_share: 1.0,
+        ..config.clone()
+    };
+    let (solutions, solutions_length) = initialize_phase(distance, cities, &construct_config, 0);
+    ResultKind {
+        best_solution: solutions[0].clone(),
+        best_solution_length: solutions_length[0],
+        solutions,
+        initial_best_length: solutions_length[0],
+        evaluations: 1,
+        stop_reason: StopReason::NoOptimize,
+        history: Vec::new(),
+    }
+}
+
+// With at most three cities every tour is the same cycle, traversed one way or the other, so only the
+// direction can matter and only for an asymmetric matrix.
+fn trivial_solution(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> ResultKind {
+    let mut solutions: Vec<Vec<usize>> = vec![(0..distance.len()).collect()];
+    if !distance.is_symmetric() || !config.time_windows.is_empty() {
+        solutions.push((0..distance.len()).rev().collect());
+    }
+    let solutions_length: Vec<f64> = solutions.iter().map(|solution| calc_solution_cost(solution, distance, cities, config)).collect();
+    let best_index = find_best_index(&solutions_length, config);
+    ResultKind {
+        best_solution: solutions[best_index].clone(),
+        best_solution_length: solutions_length[best_index],
+        initial_best_length: solutions_length[best_index],
+        evaluations: solutions.len(),
+        solutions,
+        stop_reason: StopReason::Trivial,
+        history: Vec::new(),
+    }
+}
+
+// True when every pair of cities is at distance zero, e.g. all cities share the same coordinates.
+// Stops at the first non-zero distance, so real instances are rejected almost immediately.
+fn is_zero_matrix(distance: &DistanceMatrix) -> bool {
+    (0..distance.len()).all(|city1| (0..distance.len()).all(|city2| distance.get(city1, city2) == 0.0))
+}
+
+// Every tour of a zero matrix has length zero, so the first one that keeps the fixed edges is returned.
+fn degenerate_solution(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> ResultKind {
+    let solution = repair_solution((0..distance.len()).collect(), &build_chains(&config.fixed_edges));
+    let solution_length = calc_solution_cost(&solution, distance, cities, config);
+    ResultKind {
+        best_solution: solution.clone(),
+        best_solution_length: solution_length,
+        initial_best_length: solution_length,
+        evaluations: 1,
+        solutions: vec![solution],
+        stop_reason: StopReason::Degenerate,
+        history: Vec::new(),
+    }
+}
+
+// Steps to the next permutation in lexicographic order, returning false after the last one.
+fn next_permutation(values: &mut [usize]) -> bool {
+    let Some(pivot) = (1..values.len()).rev().find(|&i| values[i - 1] < values[i]).map(|i| i - 1) else {
+        return false;
+    };
+    let successor = (pivot + 1..values.len()).rev().find(|&i| values[i] > values[pivot]).unwrap();
+    values.swap(pivot, successor);
+    values[pivot + 1..].reverse();
+    true
+}
+
+// With no more distinct tours than food sources the search cannot beat trying them all, so every tour
+// starting at city 0 is evaluated, except those breaking a fixed edge the search would repair. A tour
+// and its reverse are the same cycle unless direction matters. The best colony_size / 2 tours are kept as the population.
+fn exhaustive_solution(distance: &DistanceMatrix, cities: &[Vec<f64>], config: &ConfigKind) -> ResultKind {
+    let city_amount = distance.len();
+    let directed = !distance.is_symmetric() || !config.time_windows.is_empty();
+    let fixed_edges: Vec<(usize, usize)> = repair_chains(config).iter().flat_map(|chain| chain.windows(2).map(|edge| (edge[0].min(edge[1]), edge[0].max(edge[1])))).collect();
+    let mut solution: Vec<usize> = (0..city_amount).collect();
+    let mut solutions: Vec<Vec<usize>> = Vec::new();
+    loop {
+        if (directed || solution[1] < solution[city_amount - 1]) && fixed_edges.iter().all(|edge| tour_edges(&solution).contains(edge)) {
+            solutions.push(solution.clone());
+        }
+        if !next_permutation(&mut solution[1..]) {
+            break;
+        }
+    }
+    let evaluations = solutions.len();
+    let mut population: Vec<(Vec<usize>, f64)> = solutions
+        .into_iter()
+        .map(|solution| {
+            let solution_length = calc_solution_cost(&solution, distance, cities, config);
+            (solution, solution_length)
+        })
+        .collect();
+    population.sort_by(|(_, length1), (_, length2)| compare_length(*length1, *length2, config));
+    population.truncate(config.colony_size / 2);
+    let (best_solution, best_solution_length) = population[0].clone();
+    ResultKind {
+        best_solution,
+        best_solution_length,
+        initial_best_length: best_solution_length,
+        evaluations,
+        solutions: population.into_iter().map(|(solution, _)| solution).collect(),
+        stop_reason: StopReason::Exhaustive,
+        history: Vec::new(),
+    }
+}
+
+// Position of every input city among the cities kept by --limit-cities, None for a dropped city.
+fn kept_positions(kept_cities: &[usize], input_city_amount: usize) -> Vec<Option<usize>> {
+    let mut positions: Vec<Option<usize>> = vec![None; input_city_amount];
+    for (position, &city) in kept_cities.iter().enumerate() {
+        positions[city] = Some(position);
+    }
+    positions
+}
+
+fn solve_instance(input_path: String, output_path: String, config: &ConfigKind, arguments: &ArgumentKind) -> f64 {
+    let start_time = Instant::now();
+    let file_config = config;
+    let meta_input = arguments.meta.as_ref().map(|_| (input_path.clone(), if input_path == "-" { None } else { hash_file(&input_path) }));
+    let (mut cities, mut labels) = match arguments.labels_col {
+        Some(_) if !config.columns.is_empty() => panic!("Invalid argument: --labels-col cannot be used with columns, name the label column id instead."),
+        Some(labels_col) => {
+            let (cities, labels) = read_labeled_input(input_path, labels_col, arguments.open_retries);
+            (cities, Some(labels))
+        },
+        None if !config.columns.is_empty() => read_mapped_input(input_path, &config.columns, arguments.open_retries),
+        None => {
+            check_tsplib_distance(&input_path, config, arguments.open_retries);
+            (read_input(input_path, arguments.open_retries), None)
+        },
+    };
+    let input_city_amount = cities.len();
+    let mut time_windows = arguments.windows.as_ref().map(|windows_path| read_time_windows(windows_path, input_city_amount));
+    let kept_cities: Vec<usize> = match arguments.limit_cities {
+        Some(limit) => limit_cities(input_city_amount, limit, arguments.sample_cities, config),
+        None => (0..input_city_amount).collect(),
+    };
+    if arguments.limit_cities.is_some() {
+        cities = kept_cities.iter().map(|&city| cities[city].clone()).collect();
+        labels = labels.map(|labels| kept_cities.iter().map(|&city| labels[city].clone()).collect());
+        time_windows = time_windows.map(|time_windows| kept_cities.iter().map(|&city| time_windows[city]).collect());
+    }
+    let is_trivial = cities.len() <= TRIVIAL_CITY_AMOUNT;
+    if !is_trivial {
+        check_colony_size(config, cities.len(), arguments.strict);
+    }
+    let mut config = if is_trivial { config.clone() } else { cap_colony_size(config, cities.len()) };
+    validate_islands(&config);
+    let kept_positions = kept_positions(&kept_cities, input_city_amount);
+    if let Some(focus_path) = &arguments.focus {
+        config.focus_cities = read_focus(focus_path, input_city_amount).iter().filter_map(|&city| kept_positions[city]).collect();
+        if config.focus_cities.len() < 2 {
+            panic!("Invalid focus file.\nReason: Fewer than two focus cities are left after --limit-cities.");
+        }
+    }
+    if let Some(&(city1, city2)) = config.fixed_edges.iter().find(|&&(city1, city2)| city1.max(city2) >= input_city_amount) {
+        panic!("Invalid fixed edges.\nReason: Edge {}-{} refers to a city that does not exist.", city1, city2);
+    }
+    config.fixed_edges = config
+        .fixed_edges
+        .iter()
+        .map(|&(city1, city2)| match (kept_positions[city1], kept_positions[city2]) {
+            (Some(position1), Some(position2)) => (position1, position2),
+            _ => panic!("Invalid fixed edges.\nReason: Edge {}-{} refers to a city removed by --limit-cities.", city1, city2),
+        })
+        .collect();
+    if let Some(time_windows) = time_windows {
+        if config.optimize == OptimizeDirection::Max || config.normalize_coords != NormalizeMode::None {
+            panic!("Invalid argument: --windows requires optimize = Min and normalize_coords = None.");
+        }
+        config.time_windows = time_windows;
+    }
+    let config = &config;
+    let (distance, search_config, report_distance) = match (&arguments.matrix, &arguments.edges) {
+        (Some(_), Some(_)) => panic!("Invalid argument: --matrix and --edges cannot be used together."),
+        (Some(matrix_path), None) => {
+            let distance = read_distance_matrix(matrix_path.clone(), input_city_amount, &kept_cities, arguments.open_retries);
+            match forbid_infinite_edges(&distance) {
+                Some(_) if config.optimize == OptimizeDirection::Max => panic!("Invalid argument: A --matrix with +inf entries only supports optimize = Min."),
+                Some(search_distance) => (search_distance, config.clone(), Some(distance)),
+                None => (distance, config.clone(), None),
+            }
+        },
+        (None, Some(edges_path)) => {
+            if config.optimize == OptimizeDirection::Max {
+                panic!("Invalid argument: --edges only supports optimize = Min.");
+            }
+            let edges: HashMap<(usize, usize), f64> = read_edge_list(edges_path, input_city_amount)
+                .into_iter()
+                .filter_map(|((city1, city2), length)| Some(((kept_positions[city1]?, kept_positions[city2]?), length)))
+                .collect();
+            // Any tour with fewer missing edges is shorter than one with more, so the search first
+            // minimizes the missing edges and then the length; reports show them as infinite.
+            let missing_cost = edges.values().sum::<f64>() + 1.0;
+            let report_distance = DistanceMatrix::Sparse { city_amount: cities.len(), edges: edges.clone(), missing_cost: f64::INFINITY };
+            (DistanceMatrix::Sparse { city_amount: cities.len(), edges, missing_cost }, config.clone(), Some(report_distance))
+        },
+        (None, None) => {
+            let (normalized_cities, search_config) = normalize_coordinates(&cities, config);
+            let distance = build_distance_matrix(&normalized_cities, &search_config);
+            let report_distance = match config.normalize_coords {
+                NormalizeMode::None => None,
+                _ => Some(DistanceMatrix::Lazy { cities: cities.clone(), metric: config.distance_metric, rounding: config.distance_rounding }),
+            };
+            (distance, search_config, report_distance)
+        },
+    };
+    let search_config = scale_penalties(search_config, config, &distance);
+    let config = &search_config;
+    let report_distance = report_distance.as_ref().unwrap_or(&distance);
+    if let (Some(meta_path), Some((meta_input_path, input_hash))) = (&arguments.meta, meta_input) {
+        // The file's own settings with the colony size after cap_colony_size, so copying them back
+        // into a configuration file reproduces the run.
+        let meta_config = ConfigKind { colony_size: config.colony_size, ..file_config.clone() };
+        write_result(meta_path.clone(), format_meta(&meta_config, &meta_input_path, input_hash, arguments, config.turn_penalty));
+    }
+    if let Some(matrix_path) = &arguments.dump_matrix {
+        write_result(matrix_path.clone(), format_matrix(&distance));
+    }
+    let show_progress = arguments.progress && io::stdout().is_terminal();
+    let mut initial_solutions: Vec<Vec<usize>> = Vec::new();
+    if let Some(tour_path) = &arguments.seed_tour {
+        let seed_tour = read_tour(tour_path);
+        if let Err(e) = validate_tour(&seed_tour, cities.len()) {
+            panic!("Invalid seed tour.\nReason: {}", e);
+        }
+        initial_solutions.push(seed_tour);
+    }
+    if let Some(population_path) = &arguments.population_in {
+        initial_solutions.extend(read_population(population_path, cities.len()));
+    }
+    if arguments.no_optimize && !initial_solutions.is_empty() {
+        panic!("Invalid argument: --seed-tour and --population-in cannot be used with --no-optimize.");
+    }
+    if initial_solutions.len() > config.colony_size / 2 {
+        panic!("Invalid population file.\nReason: {} tours were given for {} food sources.", initial_solutions.len(), config.colony_size / 2);
+    }
+    let mut stream = arguments.stream.as_ref().map(|stream_path| StreamKind {
+        file: {
+            create_parent_dir(stream_path);
+            File::create(stream_path).expect("Fail create stream file.")
+        },
+        interval: arguments.log_interval,
+    });
+    let is_degenerate = !is_trivial && config.time_windows.is_empty() && is_zero_matrix(&distance);
+    if is_degenerate {
+        eprintln!("Warning: All distances between cities are zero, so every tour has length zero. Check that the input does not repeat the same coordinates.");
+    }
+    let result = if arguments.no_optimize {
+        construct_only(&distance, &cities, config)
+    } else if is_trivial {
+        trivial_solution(&distance, &cities, config)
+    } else if is_degenerate {
+        degenerate_solution(&distance, &cities, config)
+    } else if count_distinct_tours(cities.len(), config.colony_size / 2 + 1) <= config.colony_size / 2 {
+        exhaustive_solution(&distance, &cities, config)
+    } else {
+        artificial_bee_colony(&distance, report_distance, &cities, config, &initial_solutions, stream.as_mut(), arguments.debug_invariants, show_progress)
+    };
+    let best_solution = result.best_solution;
+    let best_solution_length = calc_path_length(&best_solution, report_distance);
+    if best_solution_length.is_infinite() && (arguments.edges.is_some() || arguments.matrix.is_some()) {
+        eprintln!("Warning: No tour using only allowed edges was found, the best solution uses forbidden edges.");
+    }
+    if let Some(gnuplot_path) = &arguments.gnuplot {
+        write_result(gnuplot_path.clone(), format_gnuplot(&best_solution, &cities));
+    }
+    if let Some(population_path) = &arguments.population_out {
+        write_result(population_path.clone(), format_population(&result.solutions, report_distance, config));
+    }
+    if let Some(history_path) = &arguments.history {
+        write_result(history_path.clone(), format_history(&result.history, config, arguments.iter_timing));
+    }
+    if arguments.append {
+        append_result(output_path, format_record(config, &best_solution, best_solution_length, start_time.elapsed()));
+        return best_solution_length;
+    }
+    let mut output_message = String::new();
+    let solution_format: Vec<String> = best_solution.iter().map(|city| city.to_string()).collect();
+    output_message.push_str(&format!("Best solution:{}\n", solution_format.join(" ")));
+    if let Some(labels) = &labels {
+        let label_format: Vec<&str> = best_solution.iter().map(|&city| labels[city].as_str()).collect();
+        output_message.push_str(&format!("Best solution labels:{}\n", label_format.join(",")));
+    }
+    output_message.push_str(&format!("Best solution length:{}\n", format_length(best_solution_length, config, arguments.precision)));
+    if arguments.output_inverse {
+        let inverse_format: Vec<String> = invert_solution(&best_solution).iter().map(|position| position.to_string()).collect();
+        output_message.push_str(&format!("Inverse solution:{}\n", inverse_format.join(" ")));
+    }
+    output_message.push_str(&format!("Stop reason:{:?}\n", result.stop_reason));
+    if let Some(edge_amount) = arguments.edge_freq {
+        let edge_format: Vec<String> = count_edge_frequency(&result.solutions)
+            .iter()
+            .take(edge_amount)
+            .map(|((city1, city2), count)| format!("{}-{}={}%", city1, city2, *count as f64 / result.solutions.len() as f64 * 100.0))
+            .collect();
+        output_message.push_str(&format!("Edge frequency:{}\n", edge_format.join(" ")));
+    }
+    match arguments.baseline.as_deref() {
+        Some("random") => {
+            let baseline_solution = random_search(&distance, &cities, config, result.evaluations);
+            output_message.push_str(&format!("Random baseline length:{}\n", format_length(calc_path_length(&baseline_solution, report_distance), config, arguments.precision)));
+            output_message.push_str(&format!("Baseline evaluations:{}\n", result.evaluations));
+        },
+        Some(_) => panic!("Unknown baseline."),
+        None => {},
+    }
+    if !arguments.anytime.is_empty() {
+        output_message.push_str(&format!("Anytime best length:{}\n", format_anytime(&result.history, best_solution_length, &arguments.anytime, config, arguments.precision)));
+    }
+    if let Some(optimal_path) = &arguments.optimal_tour {
+        let optimal_tour = read_tour(optimal_path);
+        if let Err(e) = validate_tour(&optimal_tour, cities.len()) {
+            panic!("Invalid optimal tour.\nReason: {}", e);
+        }
+        output_message.push_str(&format_optimal_gap(&best_solution, best_solution_length, &optimal_tour, report_distance, config, arguments.precision));
+    }
+    if arguments.stats {
+        let improvement = (result.initial_best_length - result.best_solution_length).abs() / result.initial_best_length * 100.0;
+        output_message.push_str(&format!("Improvement over initial:{}%\n", improvement));
+        let improved_sources: usize = result.history.iter().map(|iteration| iteration.improved_sources).sum();
+        let acceptance_rate = improved_sources as f64 / (result.history.len().max(1) * (config.colony_size / 2)) as f64 * 100.0;
+        output_message.push_str(&format!("Acceptance rate:{}%\n", acceptance_rate));
+        let bounding_box = calc_bounding_box(&cities);
+        let box_format: Vec<String> = bounding_box.iter().map(|extent| extent.to_string()).collect();
+        output_message.push_str(&format!("Bounding box:{}\n", box_format.join("x")));
+        if let [width, height] = bounding_box[..] {
+            let geometric_length: f64 = (0..best_solution.len())
+                .map(|i| euclidean_distance(&cities[best_solution[i]], &cities[best_solution[(i + 1) % best_solution.len()]]))
+                .sum();
+            output_message.push_str(&format!("Length to bounding box perimeter:{}\n", geometric_length / (2.0 * (width + height))));
+        }
+        output_message.push_str(&format!("Average edge length:{}\n", format_length(best_solution_length / best_solution.len() as f64, config, arguments.precision)));
+        output_message.push_str(&format!("Threads:{} ({} CPUs detected)\n", config.concurrent_count, num_cpus::get()));
+    }
+    if let Some(turn_angle) = config.turn_angle {
+        output_message.push_str(&format!("Turn count:{}\n", count_turns(&best_solution, &cities, turn_angle)));
+    }
+    if !config.time_windows.is_empty() {
+        let (lateness, late_arrivals) = calc_window_lateness(&best_solution, report_distance, &config.time_windows);
+        if late_arrivals > 0 {
+            eprintln!("Warning: The best solution has {} late arrivals.", late_arrivals);
+        }
+        output_message.push_str(&format!("Window lateness:{}\n", format_length(lateness, config, arguments.precision)));
+        output_message.push_str(&format!("Late arrivals:{}\n", late_arrivals));
+    }
+    if config.constraint_handling == ConstraintHandling::Penalty && !config.fixed_edges.is_empty() {
+        let missing_edges = count_missing_fixed_edges(&best_solution, &config.fixed_edges);
+        if missing_edges > 0 {
+            eprintln!("Warning: The best solution misses {} fixed edges.", missing_edges);
+        }
+        output_message.push_str(&format!("Missing fixed edges:{}\n", missing_edges));
+    }
+    output_message.push_str(&format!("Cost time:{:?}\n", start_time.elapsed()));
+    write_result(output_path, output_message);
+    best_solution_length
+}
+
+// Instances are solved in parallel, each with an even share of the concurrent_count threads, so a
+// directory of small instances keeps every thread busy instead of each instance running on all of them.
+fn solve_directory(input_dir: &str, output_dir: &str, config: &ConfigKind, arguments: &ArgumentKind) {
+    let mut input_paths: Vec<PathBuf> = fs::read_dir(input_dir)
+        .expect("Cannot open input directory.")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(file_extension(path).as_deref(), Some("xlsx") | Some("csv") | Some("tsp")))
+        .collect();
+    if input_paths.is_empty() {
+        panic!("No input files found in {}.", input_dir);
+    }
+    input_paths.sort();
+    if let Err(e) = fs::create_dir_all(output_dir) {
+        panic!("Failed to create output directory.\nReason: {}", e);
+    }
+    let parallel_instances = config.concurrent_count.min(input_paths.len());
+    let instance_config = ConfigKind { concurrent_count: (config.concurrent_count / parallel_instances).max(1), ..config.clone() };
+    let thread_pool = ThreadPoolBuilder::new().num_threads(parallel_instances).build().expect("Fail build thread pool.");
+    let summary_lines: Vec<String> = thread_pool.install(|| {
+        input_paths
+            .par_iter()
+            .map(|input_path| {
+                let start_time = Instant::now();
+                let instance_name = input_path.file_stem().and_then(|stem| stem.to_str()).expect("Invalid input file name.").to_string();
+                let output_path = Path::new(output_dir).join(format!("{}.txt", instance_name));
+                let best_solution_length = solve_instance(
+                    input_path.to_string_lossy().to_string(),
+                    output_path.to_string_lossy().to_string(),
+                    &instance_config,
+                    arguments,
+                );
+                format!("{}\t{}\t{:?}\n", instance_name, format_length(best_solution_length, config, arguments.precision), start_time.elapsed())
+            })
+            .collect()
+    });
+    print!("Instance\tBest solution length\tCost time\n{}", summary_lines.concat());
+}
+
+fn main() {
+    let arguments = get_arguments(env::args().skip(1));
+    if let Some(convert_path) = &arguments.convert {
+        let input_path = arguments.input.clone().expect("Missing argument: --input.");
+        convert_input(input_path, convert_path.clone(), arguments.open_retries);
+        return;
+    }
+    if let Some(diff_paths) = &arguments.diff {
+        match diff_paths.split_once(',') {
+            Some((old_path, new_path)) => diff_results(old_path.trim(), new_path.trim()),
+            None => panic!("Invalid argument: --diff needs two comma-separated paths."),
+        }
+        return;
+    }
+    if let Some(tour_path) = &arguments.verify {
+        let input_path = arguments.input.clone().expect("Missing argument: --input.");
+        let config = arguments.config.clone().map(read_config);
+        let cities = match &config {
+            Some(config) if !config.columns.is_empty() => read_mapped_input(input_path, &config.columns, arguments.open_retries).0,
+            _ => read_input(input_path, arguments.open_retries),
+        };
+        let (metric, rounding) = config.map_or((DistanceMetric::Euclidean, DistanceRounding::None), |config| (config.distance_metric, config.distance_rounding));
+        verify_tour(&cities, tour_path, metric, rounding);
+        return;
+    }
+    let config_path = arguments.config.clone().expect("Missing argument: --config.");
+    let mut config = resolve_config(config_path, &arguments);
+    validate_config(&config);
+    if arguments.show_config {
+        for (key, value) in format_config(&config) {
+            println!("{} = {}", key, value);
+        }
+        return;
+    }
+    if arguments.iter_timing && arguments.history.is_none() {
+        panic!("Invalid argument: --iter-timing needs --history.");
+    }
+    if arguments.append && (arguments.stats || !arguments.anytime.is_empty() || arguments.edge_freq.is_some() || arguments.baseline.is_some() || arguments.optimal_tour.is_some() || arguments.output_inverse || arguments.labels_col.is_some()) {
+        panic!("Invalid argument: --stats, --anytime, --edge-freq, --baseline, --optimal-tour, --output-inverse and --labels-col add output lines, which --append does not write.");
+    }
+    if let Some(input_dir) = &arguments.input_dir {
+        let output_dir = arguments.output_dir.as_deref().expect("Missing argument: --output-dir.");
+        if arguments.progress {
+            panic!("Invalid argument: --progress cannot be used with --input-dir, whose instances run in parallel.");
+        }
+        if arguments.meta.is_some() || arguments.gnuplot.is_some() || arguments.history.is_some() || arguments.seed_tour.is_some() || arguments.dump_matrix.is_some() || arguments.matrix.is_some() || arguments.stream.is_some() || arguments.population_out.is_some() || arguments.population_in.is_some() || arguments.optimal_tour.is_some() || arguments.edges.is_some() || arguments.windows.is_some() {
+            panic!("Invalid argument: --meta, --gnuplot, --history, --seed-tour, --dump-matrix, --matrix, --edges, --windows, --stream, --population-out, --population-in and --optimal-tour cannot be used with --input-dir.");
+        }
+        solve_directory(input_dir, output_dir, &config, &arguments);
+        return;
+    }
+    if arguments.autotune {
+        let input_path = arguments.input.clone().expect("Missing argument: --input.");
+        autotune(input_path, arguments.output.clone(), &config, arguments.open_retries);
+        return;
+    }
+    let input_path = arguments.input.clone().expect("Missing argument: --input.");
+    let output_path = arguments.output.clone().expect("Missing argument: --output.");
+    if arguments.meta.is_some() && config.seed.is_none() {
+        // An unseeded run cannot be repeated, so draw the seed here and record it in the metadata.
+        config.seed = Some(rand::random());
+    }
+    solve_instance(input_path, output_path, &config, &arguments);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data_path(file_name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("data").join(file_name).to_string_lossy().to_string()
+    }
+
+    fn seeded_config(colony_size: usize, max_iterations: usize) -> ConfigKind {
+        ConfigKind {
+            colony_size,
+            candidate_amount: 4,
+            max_unimproved: 10,
+            max_iterations,
+            concurrent_count: 1,
+            generation_method: GenerationMethod::Reverse,
+            seed: Some(7),
+            ..default_config()
+        }
+    }
+
+    fn test_cities(city_amount: usize, seed: u64) -> Vec<Vec<f64>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..city_amount).map(|_| vec![rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)]).collect()
+    }
+
+    #[test]
+    fn reverse_delta_matches_full_recompute() {
+        let distance = calc_cities_distance(&test_cities(12, 1), DistanceMetric::Euclidean, DistanceRounding::None);
+        let solution = seeded_solution(12, 1, 0);
+        let length = calc_path_length(&solution, &distance);
+        for city1 in 0..12 {
+            for city2 in city1..12 {
+                let reversed_length = calc_path_length(&reverse(&solution, city1, city2), &distance);
+                let delta = reverse_delta(&solution, city1, city2, &distance);
+                assert!((length + delta - reversed_length).abs() < 1e-9, "segment {}..={}", city1, city2);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_and_serial_argmin_agree() {
+        let config = ConfigKind { concurrent_count: 4, ..default_config() };
+        let mut solutions_length: Vec<f64> = (0..PARALLEL_ARGMIN_THRESHOLD * 2).map(|index| 10.0 + (index % 7) as f64).collect();
+        solutions_length[3] = f64::NAN;
+        solutions_length[5000] = 1.0;
+        solutions_length[6000] = 1.0;
+        solutions_length[7000] = f64::NAN;
+        let serial_index = (0..solutions_length.len()).reduce(|index1, index2| better_index(index1, index2, &solutions_length, &config)).unwrap();
+        assert_eq!(serial_index, 5000);
+        assert_eq!(find_best_index(&solutions_length, &config), serial_index);
+        assert_eq!(find_best_index(&solutions_length[..PARALLEL_ARGMIN_THRESHOLD], &config), 0);
+        assert_eq!(find_best_index(&[f64::NAN, f64::NAN], &config), 0);
+    }
+
+    #[test]
+    fn same_seed_gives_same_initial_population() {
+        let cities = test_cities(60, 2);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { colony_size: 200, concurrent_count: 4, seed: Some(7), ..default_config() };
+        let (solutions, solutions_length) = initialize_phase(&distance, &cities, &config, 0);
+        assert_eq!(initialize_phase(&distance, &cities, &config, 0), (solutions.clone(), solutions_length));
+        let serial_config = ConfigKind { force_serial: true, ..config.clone() };
+        assert_eq!(initialize_phase(&distance, &cities, &serial_config, 0).0, solutions);
+        let other_config = ConfigKind { seed: Some(8), ..config };
+        assert_ne!(initialize_phase(&distance, &cities, &other_config, 0).0, solutions);
+    }
+
+    #[test]
+    fn validate_tour_detects_invalid_tours() {
+        assert!(validate_tour(&[2, 0, 3, 1], 4).is_ok());
+        assert!(matches!(validate_tour(&[2, 0, 2, 1], 4), Err(TourError::Duplicate(2))));
+        assert!(matches!(validate_tour(&[2, 0, 1], 4), Err(TourError::WrongLength { expected: 4, found: 3 })));
+        assert!(matches!(validate_tour(&[2, 0, 4, 1], 4), Err(TourError::OutOfRange(4))));
+    }
+
+    #[test]
+    fn fitness_is_piecewise_around_zero() {
+        let config = default_config();
+        assert_eq!(fitness(0.0, &config), 1.0);
+        assert_eq!(fitness(3.0, &config), 0.25);
+        assert_eq!(fitness(-2.0, &config), 3.0);
+        assert!(fitness(-1e-12, &config) > fitness(0.0, &config));
+        let max_config = ConfigKind { optimize: OptimizeDirection::Max, ..default_config() };
+        assert_eq!(fitness(2.0, &max_config), 3.0);
+        assert!(fitness(5.0, &max_config) > fitness(2.0, &max_config));
+    }
+
+    #[test]
+    fn trailing_blank_lines_are_ignored() {
+        assert_eq!(read_csv("0,1,2\n1,0,3\n2,3,0\n\n  \n"), vec![vec![0.0, 1.0, 2.0], vec![1.0, 0.0, 3.0], vec![2.0, 3.0, 0.0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row 2 has 2 columns, expected 3.")]
+    fn ragged_rows_name_the_first_offender() {
+        read_csv("0,1,2\n1,0\n2,3,0\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Row 2 column 1 is not a finite number.")]
+    fn nan_coordinates_are_rejected() {
+        read_csv("0,0\nNaN,1\n2,2\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Row 3 column 2 is not a finite number.")]
+    fn infinite_coordinates_are_rejected() {
+        read_csv("0,0\n1,1\n2,inf\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Distance between city 0 and city 1 is not finite.")]
+    fn overflowing_distances_are_rejected() {
+        calc_cities_distance(&[vec![1e200, 0.0], vec![-1e200, 0.0], vec![0.0, 1.0]], DistanceMetric::Euclidean, DistanceRounding::None);
+    }
+
+    #[test]
+    #[should_panic(expected = "too wide a range for the distances between cities to be finite")]
+    fn overflowing_lazy_distances_are_rejected() {
+        let config = ConfigKind { matrix_mode: MatrixMode::Lazy, ..default_config() };
+        build_distance_matrix(&[vec![1e200, 0.0], vec![-1e200, 0.0], vec![0.0, 1.0]], &config);
+    }
+
+    #[test]
+    fn fixed_edges_are_chained_and_repaired() {
+        let chains = build_chains(&[(1, 2), (2, 3), (5, 4)]);
+        assert_eq!(chains, vec![vec![1, 2, 3], vec![4, 5]]);
+        assert_eq!(repair_solution(vec![3, 0, 5, 1, 4, 2], &chains), vec![3, 2, 1, 0, 5, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fixed edges form a cycle.")]
+    fn cyclic_fixed_edges_are_rejected() {
+        build_chains(&[(0, 1), (1, 2), (2, 0)]);
+    }
+
+    // Cities on a circle: the shortest tour follows the circle and never crosses it, the fixed edge
+    // between opposite cities forces a crossing.
+    #[test]
+    fn fixed_edge_survives_the_search() {
+        let cities: Vec<Vec<f64>> = (0..8).map(|city| {
+            let angle = city as f64 * std::f64::consts::PI / 4.0;
+            vec![angle.cos(), angle.sin()]
+        }).collect();
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { fixed_edges: vec![(0, 4)], ..seeded_config(10, 50) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert!(tour_edges(&result.best_solution).contains(&(0, 4)));
+        assert!(validate_tour(&result.best_solution, 8).is_ok());
+    }
+
+    #[test]
+    fn symmetric_storage_matches_dense_matrix() {
+        let cities = test_cities(9, 3);
+        let dense = DistanceMatrix::Full(cities.iter().map(|city1| cities.iter().map(|city2| euclidean_distance(city1, city2)).collect()).collect());
+        let symmetric = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        assert!(matches!(symmetric, DistanceMatrix::Symmetric { .. }));
+        assert_eq!(symmetric.len(), dense.len());
+        for city1 in 0..9 {
+            for city2 in 0..9 {
+                assert_eq!(symmetric.get(city1, city2), dense.get(city1, city2), "pair {}-{}", city1, city2);
+            }
+        }
+        let solution = seeded_solution(9, 3, 0);
+        assert_eq!(calc_path_length(&solution, &symmetric), calc_path_length(&solution, &dense));
+    }
+
+    // burma14 from TSPLIB, whose optimal tour has GEO length 3323.
+    #[test]
+    fn geo_distance_reproduces_tsplib_burma14() {
+        let cities: Vec<Vec<f64>> = [
+            [16.47, 96.10], [16.47, 94.44], [20.09, 92.54], [22.39, 93.37], [25.23, 97.24], [22.00, 96.05], [20.47, 97.02],
+            [17.20, 96.29], [16.30, 97.38], [14.05, 98.12], [16.53, 97.38], [21.52, 95.59], [19.41, 97.13], [20.09, 94.55],
+        ]
+        .iter()
+        .map(|city| city.to_vec())
+        .collect();
+        assert_eq!(geo_distance(&cities[0], &cities[1]), 153.0);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Geo, DistanceRounding::None);
+        let optimal_tour: Vec<usize> = [1, 2, 14, 3, 4, 5, 6, 12, 7, 13, 8, 11, 9, 10].iter().map(|city| city - 1).collect();
+        assert_eq!(calc_path_length(&optimal_tour, &distance), 3323.0);
+    }
+
+    #[test]
+    fn win_count_ties_go_to_the_better_tour() {
+        let config = default_config();
+        assert_eq!(most_selected(&[3, 1, 3, 1], &[9.0, 1.0, 4.0, 2.0], &config), 2);
+        assert_eq!(most_selected(&[2, 2, 2, 0], &[5.0, 5.0, 6.0, 1.0], &config), 0);
+        let max_config = ConfigKind { optimize: OptimizeDirection::Max, ..default_config() };
+        assert_eq!(most_selected(&[3, 1, 3, 1], &[9.0, 1.0, 4.0, 2.0], &max_config), 0);
+    }
+
+    // On a regular tetrahedron every tour has the same length, so the seeded optimal tour never
+    // improves. Each iteration evaluates the source for the Reverse delta, candidate_amount candidates
+    // and the picked tour, and a scout restart one more tour, so the evaluation count shows in which
+    // iterations the source was re-initialized.
+    #[test]
+    fn scout_restarts_after_max_unimproved_failures() {
+        let cities = vec![vec![1.0, 1.0, 1.0], vec![1.0, -1.0, -1.0], vec![-1.0, 1.0, -1.0], vec![-1.0, -1.0, 1.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let optimal_tours = vec![vec![0, 1, 2, 3]];
+        for max_iterations in 1..=6 {
+            let config = ConfigKind { max_unimproved: 1, ..seeded_config(2, max_iterations) };
+            let result = artificial_bee_colony(&distance, &distance, &cities, &config, &optimal_tours, None, false, false);
+            assert_eq!(result.stop_reason, StopReason::MaxIterations);
+            assert_eq!(result.history.iter().map(|iteration| iteration.improved_sources).sum::<usize>(), 0);
+            // The counter reaches 1 after the first failure and 2 > max_unimproved after the second,
+            // which restarts the source and resets the counter, so restarts happen every second iteration.
+            let restarts = max_iterations / 2;
+            assert_eq!(result.evaluations, 2 + (config.candidate_amount + 2) * max_iterations + restarts, "max_iterations = {}", max_iterations);
+        }
+    }
+
+    // Operators score candidates differently (Reverse from the changed edges, the others in full), but
+    // every scored tour counts, so the budget ends the run within one iteration's evaluations.
+    #[test]
+    fn evaluation_budget_stops_every_operator_on_time() {
+        let cities = test_cities(20, 8);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        for generation_method in [GenerationMethod::Swap, GenerationMethod::Insert, GenerationMethod::Reverse, GenerationMethod::PartialShuffle] {
+            let config = ConfigKind { generation_method, max_evaluations: Some(5000), require_improvement: true, ..seeded_config(20, 100000) };
+            let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+            assert_eq!(result.stop_reason, StopReason::EvaluationBudget);
+            let iteration_evaluations = config.colony_size / 2 * (config.candidate_amount + 4);
+            assert!(result.evaluations >= 5000 && result.evaluations < 5000 + iteration_evaluations, "{:?}: {}", generation_method, result.evaluations);
+        }
+    }
+
+    #[test]
+    fn zero_improvement_threshold_never_stops_early() {
+        let cities = test_cities(30, 4);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        for improvement_mode in [ImprovementMode::Relative, ImprovementMode::Absolute] {
+            let config = ConfigKind { improvement_threshold: 0.0, improvement_mode, ..seeded_config(20, 100) };
+            let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+            assert_eq!(result.stop_reason, StopReason::MaxIterations);
+            assert_eq!(result.history.len(), 100);
+            assert!(result.best_solution_length < result.initial_best_length);
+        }
+    }
+
+    #[test]
+    fn only_lock_errors_are_retried() {
+        assert!(is_transient_open_error(&io::Error::from(io::ErrorKind::ResourceBusy)));
+        assert!(is_transient_open_error(&io::Error::from(io::ErrorKind::Interrupted)));
+        assert!(!is_transient_open_error(&io::Error::from(io::ErrorKind::NotFound)));
+        assert!(!is_transient_open_error(&io::Error::from(io::ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn locked_file_is_opened_after_retries() {
+        let mut attempts = 0;
+        let opened = retry_open("locked.xlsx", 3, || {
+            attempts += 1;
+            if attempts < 3 { Err(io::Error::from(io::ErrorKind::ResourceBusy)) } else { Ok(attempts) }
+        });
+        assert_eq!(opened, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "The file may be open in another program")]
+    fn locked_file_fails_once_retries_run_out() {
+        retry_open("locked.xlsx", 1, || Err::<(), _>(io::Error::from(io::ErrorKind::ResourceBusy)));
+    }
+
+    #[test]
+    fn missing_file_is_not_retried() {
+        let mut attempts = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            retry_open("missing.xlsx", 3, || {
+                attempts += 1;
+                Err::<(), _>(io::Error::from(io::ErrorKind::NotFound))
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    // Driving around the square, city 2 is reached after 20 but closes at 15, and city 1 opens only at
+    // 25. The feasible tour crosses the square to city 2 first and is longer than the shortest tour.
+    #[test]
+    fn time_windows_prefer_a_longer_feasible_tour() {
+        let cities = vec![vec![0.0, 0.0], vec![10.0, 0.0], vec![10.0, 10.0], vec![0.0, 10.0], vec![5.0, -3.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let time_windows = vec![(0.0, 100.0), (25.0, 1000.0), (0.0, 15.0), (0.0, 1000.0), (0.0, 1000.0)];
+        let shortest_tour = vec![0, 4, 1, 2, 3];
+        assert!(calc_window_lateness(&shortest_tour, &distance, &time_windows).0 > 0.0);
+        assert!(calc_window_lateness(&[0, 3, 2, 1, 4], &distance, &time_windows).0 > 0.0);
+        let config = ConfigKind { time_windows: time_windows.clone(), ..seeded_config(10, 100) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert_eq!(calc_window_lateness(&result.best_solution, &distance, &time_windows), (0.0, 0));
+        assert!(calc_path_length(&result.best_solution, &distance) > calc_path_length(&shortest_tour, &distance));
+    }
+
+    // Share of tours that keep every fixed edge and arrive within every window.
+    fn feasibility_rate(solutions: &[Vec<usize>], distance: &DistanceMatrix, config: &ConfigKind) -> f64 {
+        let feasible = solutions
+            .iter()
+            .filter(|solution| count_missing_fixed_edges(solution, &config.fixed_edges) == 0)
+            .filter(|solution| config.time_windows.is_empty() || calc_window_lateness(solution, distance, &config.time_windows).1 == 0)
+            .count();
+        feasible as f64 / solutions.len() as f64
+    }
+
+    // Repair keeps every tour feasible where it can, Penalty only makes infeasible tours expensive, so
+    // the best tour is feasible either way but Repair's population is feasible throughout.
+    #[test]
+    fn constraint_handling_modes_reach_feasible_tours() {
+        let cities: Vec<Vec<f64>> = (0..8).map(|city| {
+            let angle = city as f64 * std::f64::consts::PI / 4.0;
+            vec![angle.cos(), angle.sin()]
+        }).collect();
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let repair_config = ConfigKind { fixed_edges: vec![(0, 4)], ..seeded_config(10, 50) };
+        let penalty_config = ConfigKind { constraint_handling: ConstraintHandling::Penalty, ..repair_config.clone() };
+        let repair_result = artificial_bee_colony(&distance, &distance, &cities, &repair_config, &[], None, false, false);
+        let penalty_result = artificial_bee_colony(&distance, &distance, &cities, &penalty_config, &[], None, false, false);
+        assert_eq!(feasibility_rate(&initialize_phase(&distance, &cities, &repair_config, 0).0, &distance, &repair_config), 1.0);
+        assert!(feasibility_rate(&initialize_phase(&distance, &cities, &penalty_config, 0).0, &distance, &penalty_config) < 1.0);
+        assert_eq!(feasibility_rate(&repair_result.solutions, &distance, &repair_config), 1.0);
+        assert_eq!(count_missing_fixed_edges(&repair_result.best_solution, &[(0, 4)]), 0);
+        assert_eq!(count_missing_fixed_edges(&penalty_result.best_solution, &[(0, 4)]), 0);
+        assert_eq!(calc_path_length(&penalty_result.best_solution, &distance), calc_path_length(&repair_result.best_solution, &distance));
+        // Without a penalty the fixed edge is free to break, and the circle itself is shorter.
+        let free_config = ConfigKind { fixed_edge_penalty: 0.0, ..penalty_config };
+        let free_result = artificial_bee_colony(&distance, &distance, &cities, &free_config, &[], None, false, false);
+        assert_eq!(count_missing_fixed_edges(&free_result.best_solution, &[(0, 4)]), 1);
+
+        let cities = test_cities(12, 11);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        // Cities 3, 7 and 9 are only on time when visited first, in this order.
+        let mut time_windows = vec![(0.0, 10000.0); 12];
+        let mut latest = 0.0;
+        for (previous, city) in [(0, 3), (3, 7), (7, 9)] {
+            latest += distance.get(previous, city);
+            time_windows[city] = (0.0, latest + 1e-6);
+        }
+        let penalty_config = ConfigKind { time_windows, constraint_handling: ConstraintHandling::Penalty, ..seeded_config(40, 100) };
+        let repair_config = ConfigKind { constraint_handling: ConstraintHandling::Repair, ..penalty_config.clone() };
+        let repair_rate = feasibility_rate(&initialize_phase(&distance, &cities, &repair_config, 0).0, &distance, &repair_config);
+        let penalty_rate = feasibility_rate(&initialize_phase(&distance, &cities, &penalty_config, 0).0, &distance, &penalty_config);
+        assert!(repair_rate > penalty_rate, "repair {} penalty {}", repair_rate, penalty_rate);
+        for config in [&repair_config, &penalty_config] {
+            let result = artificial_bee_colony(&distance, &distance, &cities, config, &[], None, false, false);
+            assert_eq!(calc_window_lateness(&result.best_solution, &distance, &config.time_windows).1, 0);
+        }
+    }
+
+    fn shared_edges(solutions: &[Vec<usize>]) -> usize {
+        let edges: Vec<HashSet<(usize, usize)>> = solutions.iter().map(|solution| tour_edges(solution)).collect();
+        (0..edges.len()).flat_map(|index1| (0..index1).map(move |index2| (index1, index2))).map(|(index1, index2)| edges[index1].intersection(&edges[index2]).count()).sum()
+    }
+
+    #[test]
+    fn spread_population_shares_fewer_edges_than_random() {
+        let cities = test_cities(12, 5);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let random_config = ConfigKind { colony_size: 40, seed: Some(7), ..default_config() };
+        let spread_config = ConfigKind { init_method: InitMethod::Spread, ..random_config.clone() };
+        let random_solutions = initialize_phase(&distance, &cities, &random_config, 0).0;
+        let spread_solutions = initialize_phase(&distance, &cities, &spread_config, 0).0;
+        assert!(spread_solutions.iter().all(|solution| validate_tour(solution, 12).is_ok()));
+        assert!(shared_edges(&spread_solutions) < shared_edges(&random_solutions));
+    }
+
+    #[test]
+    fn identical_cities_stop_as_degenerate() {
+        let cities = vec![vec![3.0, 4.0]; 40];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        assert!(is_zero_matrix(&distance));
+        assert!(!is_zero_matrix(&calc_cities_distance(&test_cities(5, 6), DistanceMetric::Euclidean, DistanceRounding::None)));
+        let config = ConfigKind { fixed_edges: vec![(0, 39)], ..seeded_config(20, 1000) };
+        let result = degenerate_solution(&distance, &cities, &config);
+        assert_eq!(result.evaluations, 1);
+        assert!(validate_tour(&result.best_solution, 40).is_ok());
+        assert!(tour_edges(&result.best_solution).contains(&(0, 39)));
+        // Through the whole pipeline, a run that would take a billion iterations returns at once.
+        let temp_path = |name: &str| env::temp_dir().join(format!("abc_degenerate_{}_{}", process::id(), name)).to_string_lossy().to_string();
+        let (input_path, output_path) = (temp_path("input.csv"), temp_path("output.txt"));
+        write_result(input_path.clone(), "3,4\n".repeat(40));
+        let config = seeded_config(20, 1_000_000_000);
+        let best_solution_length = solve_instance(input_path.clone(), output_path.clone(), &config, &default_arguments());
+        let output_content = fs::read_to_string(&output_path).expect("Fail read degenerate output.");
+        fs::remove_file(&input_path).ok();
+        fs::remove_file(&output_path).ok();
+        assert_eq!(best_solution_length, 0.0);
+        assert!(output_content.contains("Stop reason:Degenerate\n"));
+    }
+
+    // A regular pentagon listed out of order: the shortest tour follows the perimeter. Capped to its 12
+    // distinct tours, the instance is solved by trying them all instead of running a billion iterations.
+    #[test]
+    fn capped_tiny_instances_are_solved_exhaustively() {
+        let pentagon: Vec<Vec<f64>> = [0, 2, 4, 1, 3].iter().map(|&corner| {
+            let angle = corner as f64 * 2.0 * std::f64::consts::PI / 5.0;
+            vec![angle.cos(), angle.sin()]
+        }).collect();
+        let square = vec![vec![0.0, 0.0], vec![1.0, 1.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        let side = euclidean_distance(&pentagon[0], &pentagon[3]);
+        for (cities, optimal_length) in [(pentagon, 5.0 * side), (square, 4.0)] {
+            let temp_path = |name: &str| env::temp_dir().join(format!("abc_exhaustive_{}_{}_{}", process::id(), cities.len(), name)).to_string_lossy().to_string();
+            let (input_path, output_path) = (temp_path("input.csv"), temp_path("output.txt"));
+            let city_format: Vec<String> = cities.iter().map(|city| format!("{},{}", city[0], city[1])).collect();
+            write_result(input_path.clone(), city_format.join("\n"));
+            let config = seeded_config(100, 1_000_000_000);
+            let best_solution_length = solve_instance(input_path.clone(), output_path.clone(), &config, &default_arguments());
+            let output_content = fs::read_to_string(&output_path).expect("Fail read exhaustive output.");
+            fs::remove_file(&input_path).ok();
+            fs::remove_file(&output_path).ok();
+            assert!((best_solution_length - optimal_length).abs() < 1e-9, "{} cities: {}", cities.len(), best_solution_length);
+            assert!(output_content.contains("Stop reason:Exhaustive\n"));
+        }
+        let distance = calc_cities_distance(&test_cities(5, 9), DistanceMetric::Euclidean, DistanceRounding::None);
+        let result = exhaustive_solution(&distance, &[], &ConfigKind { fixed_edges: vec![(1, 3)], ..seeded_config(8, 10) });
+        assert_eq!(result.evaluations, 6);
+        assert_eq!(result.solutions.len(), 4);
+        assert!(result.solutions.iter().all(|solution| tour_edges(solution).contains(&(1, 3))));
+    }
+
+    #[test]
+    fn capping_keeps_every_island_valid() {
+        let config = ConfigKind { multistart: 8, migration_count: 3, ..seeded_config(100, 10) };
+        let capped_config = cap_colony_size(&config, 5);
+        assert_eq!((capped_config.colony_size, capped_config.multistart), (24, 3));
+        validate_islands(&capped_config);
+        let capped_config = cap_colony_size(&ConfigKind { multistart: 4, ..config }, 4);
+        assert_eq!((capped_config.colony_size, capped_config.multistart), (6, 1));
+        validate_islands(&capped_config);
+    }
+
+    // Two islands of three: each island's best tour replaces the worst tour of the other one.
+    #[test]
+    fn migration_carries_the_best_tour_across_islands() {
+        let mut solutions: Vec<Vec<usize>> = (0..6).map(|index| vec![index; 3]).collect();
+        let mut solutions_length = vec![5.0, 1.0, 7.0, 4.0, 9.0, 6.0];
+        let mut unimproved_times = vec![3; 6];
+        let config = ConfigKind { multistart: 2, migration_count: 1, ..seeded_config(12, 10) };
+        migrate(&mut solutions, &mut solutions_length, &mut unimproved_times, &config);
+        assert_eq!(solutions[4], vec![1; 3]);
+        assert_eq!(solutions[2], vec![3; 3]);
+        assert_eq!(solutions_length, vec![5.0, 1.0, 4.0, 4.0, 1.0, 6.0]);
+        assert_eq!(unimproved_times, vec![3, 3, 0, 3, 0, 3]);
+    }
+
+    // A ring through 30 cities in shuffled order, with edges of length 1 to 10, is the only tour that
+    // uses listed edges alone. Random tours would miss almost all of them, walks along the edges find it.
+    #[test]
+    fn sparse_search_finds_the_hamiltonian_ring() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let mut ring: Vec<usize> = (0..30).collect();
+        ring.shuffle(&mut rng);
+        let mut edges: HashMap<(usize, usize), f64> = HashMap::new();
+        for position in 0..30 {
+            let length = rng.gen_range(1..=10) as f64;
+            edges.insert((ring[position], ring[(position + 1) % 30]), length);
+            edges.insert((ring[(position + 1) % 30], ring[position]), length);
+        }
+        let ring_length: f64 = (0..30).map(|position| edges[&(ring[position], ring[(position + 1) % 30])]).sum();
+        let missing_cost = edges.values().sum::<f64>() + 1.0;
+        let distance = DistanceMatrix::Sparse { city_amount: 30, edges: edges.clone(), missing_cost };
+        let report_distance = DistanceMatrix::Sparse { city_amount: 30, edges, missing_cost: f64::INFINITY };
+        let cities = test_cities(30, 10);
+        let config = ConfigKind { candidate_amount: 10, max_unimproved: 20, generation_method: GenerationMethod::Swap, improvement_threshold: 0.01, ..seeded_config(20, 100) };
+        let (_, solutions_length) = initialize_phase(&distance, &cities, &config, 0);
+        assert!(solutions_length.iter().all(|&solution_length| solution_length == ring_length));
+        let result = artificial_bee_colony(&distance, &report_distance, &cities, &config, &[], None, false, false);
+        assert_eq!(calc_path_length(&result.best_solution, &report_distance), ring_length);
+    }
+
+    // The matrix forbids every edge around the hexagon, the geometrically shortest tour, and makes the
+    // others cost 1, so the best tour crosses the hexagon on all six edges.
+    #[test]
+    fn infinite_matrix_entries_are_forbidden_edges() {
+        let distance = read_distance_matrix(test_data_path("hexagon_forbidden_matrix.csv"), 6, &(0..6).collect::<Vec<usize>>(), 0);
+        assert_eq!(distance.get(0, 1), f64::INFINITY);
+        let search_distance = forbid_infinite_edges(&distance).unwrap();
+        assert_eq!(search_distance.get(0, 1), 18.0 + 1.0);
+        assert_eq!(search_distance.get(0, 2), 1.0);
+        assert!(forbid_infinite_edges(&search_distance).is_none());
+        let output_path = env::temp_dir().join(format!("abc_forbidden_{}.txt", process::id())).to_string_lossy().to_string();
+        let arguments = ArgumentKind { matrix: Some(test_data_path("hexagon_forbidden_matrix.csv")), ..default_arguments() };
+        let best_solution_length = solve_instance(test_data_path("hexagon.csv"), output_path.clone(), &seeded_config(10, 100), &arguments);
+        fs::remove_file(&output_path).ok();
+        assert_eq!(best_solution_length, 6.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row 1 column 2 is neither a finite number nor +inf.")]
+    fn negative_infinite_matrix_entries_are_rejected() {
+        let matrix_path = env::temp_dir().join(format!("abc_negative_matrix_{}.csv", process::id())).to_string_lossy().to_string();
+        write_result(matrix_path.clone(), String::from("0,-inf,1\n1,0,1\n1,1,0\n"));
+        let result = std::panic::catch_unwind(|| {
+            read_distance_matrix(matrix_path.clone(), 3, &[0, 1, 2], 0);
+        });
+        fs::remove_file(&matrix_path).ok();
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    // Runs the committed instance through the whole pipeline. If a change of results is intended,
+    // regenerate tests/data/golden_expected.txt from the binary and review the difference.
+    #[test]
+    fn golden_instance_reproduces_expected_output() {
+        let config = read_config(test_data_path("golden_config.txt"));
+        validate_config(&config);
+        let arguments = ArgumentKind { edge_freq: Some(3), ..default_arguments() };
+        let output_path = env::temp_dir().join(format!("abc_golden_{}.txt", process::id())).to_string_lossy().to_string();
+        solve_instance(test_data_path("golden.csv"), output_path.clone(), &config, &arguments);
+        let output_content = fs::read_to_string(&output_path).expect("Fail read golden output.");
+        fs::remove_file(&output_path).ok();
+        let output_lines: Vec<&str> = output_content.lines().filter(|line| !line.starts_with("Cost time:")).collect();
+        let expected_content = fs::read_to_string(test_data_path("golden_expected.txt")).expect("Fail read golden file.");
+        assert_eq!(output_lines, expected_content.lines().collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn stream_content_is_read_as_csv() {
+        let content = read_stream_content(io::Cursor::new(b"0,0\n3,4\n".to_vec()));
+        assert_eq!(read_csv(&content), vec![vec![0.0, 0.0], vec![3.0, 4.0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "XLSX input cannot be read from stdin")]
+    fn xlsx_stream_content_is_rejected() {
+        read_stream_content(io::Cursor::new(b"PK\x03\x04rest".to_vec()));
+    }
+
+    fn command_line(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|argument| argument.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_arguments_are_parsed() {
+        let arguments = get_arguments(command_line(&["--input=cities.csv", "--threads=3", "--stats", "--anytime=50,100", "--output='out.txt'"]));
+        assert_eq!(arguments.input.as_deref(), Some("cities.csv"));
+        assert_eq!(arguments.output.as_deref(), Some("out.txt"));
+        assert_eq!(arguments.threads, Some(3));
+        assert!(arguments.stats);
+        assert_eq!(arguments.anytime, vec![50.0, 100.0]);
+        assert!(!arguments.append);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown argument: --bogus=1")]
+    fn unknown_arguments_are_rejected() {
+        get_arguments(command_line(&["--bogus=1"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid argument: --input")]
+    fn arguments_missing_a_value_are_rejected() {
+        get_arguments(command_line(&["--input"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid argument: --threads.")]
+    fn invalid_argument_values_are_rejected() {
+        get_arguments(command_line(&["--threads=0"]));
+    }
+
+    #[test]
+    fn tsplib_euc_2d_reproduces_berlin52_optimum() {
+        let cities = read_input(test_data_path("berlin52.tsp"), 0);
+        assert_eq!(cities.len(), 52);
+        assert_eq!(cities[1], vec![25.0, 185.0]);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::Nearest);
+        assert_eq!(distance.get(0, 1), 666.0);
+        assert_eq!(distance.get(0, 2), 281.0);
+        let optimal_tour = read_tour(&test_data_path("berlin52.opt.tour"));
+        assert_eq!(calc_path_length(&optimal_tour, &distance), 7542.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "EDGE_WEIGHT_TYPE ATT is not supported")]
+    fn unsupported_tsplib_edge_weight_types_are_rejected() {
+        read_tsplib("DIMENSION: 2\nEDGE_WEIGHT_TYPE: ATT\nNODE_COORD_SECTION\n1 0 0\n2 3 4\nEOF\n");
+    }
+
+    #[test]
+    fn input_directory_instances_are_all_solved() {
+        let input_dir = env::temp_dir().join(format!("abc_input_dir_{}", process::id()));
+        let output_dir = input_dir.join("results");
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::write(input_dir.join("square.csv"), "0,0\n1,0\n1,1\n0,1\n").unwrap();
+        fs::write(input_dir.join("triangle.tsp"), "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n2 3 0\n3 0 4\nEOF\n").unwrap();
+        fs::write(input_dir.join("notes.txt"), "not an instance\n").unwrap();
+        let config = ConfigKind { concurrent_count: 4, distance_rounding: DistanceRounding::Nearest, ..seeded_config(4, 10) };
+        solve_directory(&input_dir.to_string_lossy(), &output_dir.to_string_lossy(), &config, &default_arguments());
+        let square_result = fs::read_to_string(output_dir.join("square.txt")).unwrap();
+        let triangle_result = fs::read_to_string(output_dir.join("triangle.txt")).unwrap();
+        let result_amount = fs::read_dir(&output_dir).unwrap().count();
+        fs::remove_dir_all(&input_dir).unwrap();
+        assert!(square_result.contains("Best solution length:4\n"), "{}", square_result);
+        assert!(triangle_result.contains("Best solution length:12\n"), "{}", triangle_result);
+        assert_eq!(result_amount, 2);
+    }
+
+    #[test]
+    fn lazy_and_dense_matrices_give_identical_tours() {
+        let cities = test_cities(20, 3);
+        let dense_config = ConfigKind { matrix_mode: MatrixMode::Dense, ..seeded_config(10, 50) };
+        let lazy_config = ConfigKind { matrix_mode: MatrixMode::Lazy, ..seeded_config(10, 50) };
+        let dense = build_distance_matrix(&cities, &dense_config);
+        let lazy = build_distance_matrix(&cities, &lazy_config);
+        assert!(matches!(lazy, DistanceMatrix::Lazy { .. }));
+        let dense_result = artificial_bee_colony(&dense, &dense, &cities, &dense_config, &[], None, false, false);
+        let lazy_result = artificial_bee_colony(&lazy, &lazy, &cities, &lazy_config, &[], None, false, false);
+        assert_eq!(dense_result.best_solution, lazy_result.best_solution);
+        assert_eq!(dense_result.best_solution_length, lazy_result.best_solution_length);
+    }
+
+    // Turns the config object of a --meta file back into config file lines.
+    fn meta_config_lines(meta_message: &str) -> String {
+        let (_, config_object) = meta_message.split_once("\"config\": {").unwrap();
+        config_object
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter_map(|line| line.split_once(": "))
+            .map(|(key, value)| format!("{} = {}\n", key.trim_matches('"'), value.trim_matches('"')))
+            .collect()
+    }
+
+    #[test]
+    fn meta_config_round_trips_into_the_same_config() {
+        let config = read_config(test_data_path("meta_config.txt"));
+        assert_eq!(config.fixed_edges, vec![(0, 1), (2, 3)]);
+        let meta_message = format_meta(&config, "cities.csv", None, &default_arguments(), config.turn_penalty);
+        let config_path = env::temp_dir().join(format!("abc_meta_config_{}.txt", process::id()));
+        fs::write(&config_path, meta_config_lines(&meta_message)).unwrap();
+        let round_trip_config = read_config(config_path.to_string_lossy().to_string());
+        fs::remove_file(&config_path).ok();
+        assert_eq!(round_trip_config, config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Food source 1 is not a valid tour.")]
+    fn invariant_check_catches_a_duplicated_city() {
+        let cities = test_cities(8, 4);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = seeded_config(4, 10);
+        let (mut solutions, solutions_length) = initialize_phase(&distance, &cities, &config, 0);
+        check_invariants(&solutions, &solutions_length, &[0, 0], &distance, &cities, &config);
+        solutions[1][0] = solutions[1][1];
+        check_invariants(&solutions, &solutions_length, &[0, 0], &distance, &cities, &config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Food source 0 has length")]
+    fn invariant_check_catches_a_stale_length() {
+        let cities = test_cities(8, 4);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = seeded_config(4, 10);
+        let (solutions, mut solutions_length) = initialize_phase(&distance, &cities, &config, 0);
+        check_invariants(&solutions, &solutions_length, &[0, 0], &distance, &cities, &config);
+        solutions_length[0] += 1.0;
+        check_invariants(&solutions, &solutions_length, &[0, 0], &distance, &cities, &config);
+    }
+
+    #[test]
+    fn shown_config_resolves_default_candidate_amount() {
+        let config = read_config(test_data_path("default_candidates_config.txt"));
+        let config_entries = format_config(&config);
+        assert!(config_entries.contains(&("candidate_amount", "15".to_string())));
+        assert!(config_entries.contains(&("concurrent_count", "2".to_string())));
+    }
+
+    fn search_state(iteration: usize, evaluations: usize, improvement: Option<f64>, since_improvement_secs: u64) -> SearchStateKind {
+        SearchStateKind { iteration, evaluations, improvement, since_improvement: Duration::from_secs(since_improvement_secs) }
+    }
+
+    #[test]
+    fn max_iterations_criterion_fires_at_the_limit() {
+        let stopping_criteria = build_stopping_criteria(&seeded_config(4, 10));
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(9, 1000, Some(0.0), 1000)), None);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(10, 0, None, 0)), Some(StopReason::MaxIterations));
+    }
+
+    #[test]
+    fn improvement_threshold_criterion_fires_below_the_threshold() {
+        let stopping_criteria = build_stopping_criteria(&ConfigKind { improvement_threshold: 0.5, ..seeded_config(4, 10) });
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 0, None, 0)), None);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 0, Some(0.5), 0)), None);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 0, Some(0.4), 0)), Some(StopReason::ImprovementThreshold));
+        let disabled_criteria = build_stopping_criteria(&seeded_config(4, 10));
+        assert_eq!(check_stopping_criteria(&disabled_criteria, &search_state(1, 0, Some(0.0), 0)), None);
+    }
+
+    #[test]
+    fn evaluation_budget_criterion_fires_once_spent() {
+        let stopping_criteria = build_stopping_criteria(&ConfigKind { max_evaluations: Some(100), ..seeded_config(4, 10) });
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 99, None, 0)), None);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 100, None, 0)), Some(StopReason::EvaluationBudget));
+    }
+
+    #[test]
+    fn stagnant_criterion_fires_after_the_idle_time() {
+        let stopping_criteria = build_stopping_criteria(&ConfigKind { no_improve_secs: Some(5.0), ..seeded_config(4, 10) });
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 0, None, 4)), None);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(1, 0, None, 5)), Some(StopReason::Stagnant));
+    }
+
+    #[test]
+    fn first_listed_criterion_wins_when_several_fire() {
+        let config = ConfigKind { improvement_threshold: 0.5, max_evaluations: Some(100), no_improve_secs: Some(5.0), ..seeded_config(4, 10) };
+        let stopping_criteria = build_stopping_criteria(&config);
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(10, 100, Some(0.1), 5)), Some(StopReason::ImprovementThreshold));
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(10, 100, None, 5)), Some(StopReason::EvaluationBudget));
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(10, 0, None, 5)), Some(StopReason::Stagnant));
+        assert_eq!(check_stopping_criteria(&stopping_criteria, &search_state(10, 0, None, 0)), Some(StopReason::MaxIterations));
+    }
+
+    #[test]
+    fn seeded_sources_evolve_identically_when_built_twice() {
+        let cities = test_cities(15, 2);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = seeded_config(8, 10);
+        let evolve = || {
+            let (mut solutions, _) = initialize_phase(&distance, &cities, &config, 0);
+            let mut rngs: Vec<SmallRng> = (0..4).map(|index| source_rng(&config, index)).collect();
+            for _ in 0..5 {
+                let best_solution = solutions[0].clone();
+                solutions = exploration_phase(&solutions, &best_solution, 0.0, &mut rngs, &distance, &cities, &config).0;
+            }
+            solutions
+        };
+        let first_solutions = evolve();
+        assert_eq!(first_solutions, evolve());
+        assert!(first_solutions.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn seeded_restarts_are_reproducible() {
+        let cities = test_cities(15, 5);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        // max_unimproved = 1 sends abandoned sources to the scouts almost every iteration, and
+        // global_restart_after rebuilds the whole colony when the best tour stalls.
+        let config = ConfigKind { max_unimproved: 1, global_restart_after: Some(3), ..seeded_config(8, 60) };
+        let first_result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let second_result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert!(first_result.evaluations > 4 + 4 * (config.candidate_amount + 2) * config.max_iterations);
+        assert_eq!(first_result.solutions, second_result.solutions);
+        assert_eq!(first_result.best_solution, second_result.best_solution);
+        assert_eq!(first_result.evaluations, second_result.evaluations);
+    }
+
+    #[test]
+    fn tournament_of_four_always_picks_the_best_candidate() {
+        let config = ConfigKind { tournament_size: 4, ..seeded_config(4, 10) };
+        let mut rng = SmallRng::seed_from_u64(3);
+        for _ in 0..50 {
+            assert_eq!(onlooker_bee(&[7.0, 3.0, 9.0, 5.0], &mut rng, &config), 1);
+        }
+    }
+
+    #[test]
+    fn tournament_of_two_never_picks_the_worst_candidate() {
+        let config = ConfigKind { tournament_size: 2, ..seeded_config(4, 10) };
+        let mut rng = SmallRng::seed_from_u64(3);
+        let picks: Vec<usize> = (0..200).map(|_| onlooker_bee(&[7.0, 3.0, 9.0, 5.0], &mut rng, &config)).collect();
+        assert!(!picks.contains(&2));
+        assert!(picks.iter().any(|&pick| pick != 1));
+    }
+
+    #[test]
+    fn employed_bee_yields_valid_tours_for_every_tournament_size() {
+        let cities = test_cities(12, 8);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        for tournament_size in [2, 4] {
+            let config = ConfigKind { tournament_size, ..seeded_config(4, 10) };
+            let mut rng = SmallRng::seed_from_u64(tournament_size as u64);
+            let mut solution: Vec<usize> = (0..12).collect();
+            for _ in 0..50 {
+                solution = employed_bee(&solution, &solution, 0.0, &mut rng, &distance, &cities, &config).0;
+                assert!(validate_tour(&solution, 12).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn required_improvement_never_worsens_a_source() {
+        let cities = test_cities(15, 11);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        for generation_method in [GenerationMethod::Swap, GenerationMethod::Reverse] {
+            let config = ConfigKind { require_improvement: true, tournament_size: 2, generation_method, ..seeded_config(4, 10) };
+            let mut rng = SmallRng::seed_from_u64(5);
+            let mut solution: Vec<usize> = (0..15).collect();
+            let mut solution_length = calc_path_length(&solution, &distance);
+            let initial_length = solution_length;
+            for _ in 0..200 {
+                solution = employed_bee(&solution, &solution, 0.0, &mut rng, &distance, &cities, &config).0;
+                let new_length = calc_path_length(&solution, &distance);
+                assert!(new_length <= solution_length);
+                solution_length = new_length;
+            }
+            assert!(solution_length < initial_length);
+        }
+    }
+
+    #[test]
+    fn matrix_size_within_the_limit_is_accepted() {
+        // 1000 cities need 499500 distances of 8 bytes.
+        check_matrix_size(1000, &ConfigKind { max_matrix_bytes: Some(3_996_000), ..default_config() });
+    }
+
+    #[test]
+    #[should_panic(expected = "Reason: 1001 cities need 4004000 bytes, the limit is 3996000 bytes.")]
+    fn oversized_matrix_is_rejected_before_allocating() {
+        check_matrix_size(1001, &ConfigKind { max_matrix_bytes: Some(3_996_000), ..default_config() });
+    }
+
+    #[test]
+    #[should_panic(expected = "need more than usize::MAX bytes")]
+    fn overflowing_matrix_size_is_rejected() {
+        check_matrix_size(usize::MAX / 2, &ConfigKind { max_matrix_bytes: Some(usize::MAX), ..default_config() });
+    }
+
+    #[test]
+    fn turns_are_counted_on_a_grid() {
+        let cities = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![2.0, 0.0], vec![2.0, 1.0], vec![1.0, 1.0], vec![0.0, 1.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let perimeter = vec![0, 1, 2, 3, 4, 5];
+        let zigzag = vec![0, 5, 4, 1, 2, 3];
+        assert_eq!(count_turns(&perimeter, &cities, 45.0), 4);
+        assert_eq!(count_turns(&zigzag, &cities, 45.0), 6);
+        assert_eq!(count_turns(&zigzag, &cities, 100.0), 2);
+        let config = ConfigKind { turn_angle: Some(45.0), turn_penalty: 10.0, ..seeded_config(4, 10) };
+        assert_eq!(calc_solution_cost(&perimeter, &distance, &cities, &config), 6.0 + 40.0);
+    }
+
+    #[test]
+    fn path_length_matches_an_explicit_closing_edge() {
+        let cities = test_cities(20, 13);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let mut rng = StdRng::seed_from_u64(13);
+        for city_amount in [2, 3, 20] {
+            let mut solution: Vec<usize> = (0..city_amount).collect();
+            solution.shuffle(&mut rng);
+            let mut explicit_length = distance.get(solution[city_amount - 1], solution[0]);
+            for i in 0..city_amount - 1 {
+                explicit_length += distance.get(solution[i], solution[i + 1]);
+            }
+            assert!((calc_path_length(&solution, &distance) - explicit_length).abs() < 1e-9);
+        }
+    }
+
+    // On 9 cities, 50 iterations of greedy swaps without scouts try every swap, so the colony sits in
+    // a local optimum for good; the restart replaces the stuck source with fresh tours that descend
+    // to a better one.
+    #[test]
+    fn global_restart_escapes_a_local_optimum() {
+        let cities = test_cities(9, 4);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { generation_method: GenerationMethod::Swap, require_improvement: true, max_unimproved: usize::MAX, seed: Some(4), ..seeded_config(4, 1000) };
+        let stuck_result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let last_improvement = stuck_result.history.iter().rposition(|iteration| iteration.best_tour_length != stuck_result.best_solution_length);
+        assert!(last_improvement.is_some_and(|last_improvement| last_improvement < 50));
+        let restart_config = ConfigKind { global_restart_after: Some(50), ..config };
+        let restart_result = artificial_bee_colony(&distance, &distance, &cities, &restart_config, &[], None, false, false);
+        assert!(restart_result.best_solution_length < stuck_result.best_solution_length);
+    }
+
+    #[test]
+    fn inverse_composed_with_the_tour_is_the_identity() {
+        let mut rng = StdRng::seed_from_u64(25);
+        let mut solution: Vec<usize> = (0..25).collect();
+        solution.shuffle(&mut rng);
+        let inverse = invert_solution(&solution);
+        assert!((0..25).all(|city| solution[inverse[city]] == city));
+        assert!((0..25).all(|position| inverse[solution[position]] == position));
+    }
+
+    #[test]
+    fn colony_size_at_the_suggestion_is_accepted() {
+        // 100 cities suggest 2 * sqrt(100) = 20 bees.
+        check_colony_size(&seeded_config(20, 10), 100, true);
+        check_colony_size(&seeded_config(40, 10), 100, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Colony size 18 is small for 100 cities, consider at least 20.")]
+    fn colony_size_below_the_suggestion_is_reported() {
+        check_colony_size(&seeded_config(18, 10), 100, true);
+    }
+
+    // Two pairs of cities far apart with one city between them above and below: every source ends
+    // on the same shortest tour, so its six edges are the backbone and no other edge is left.
+    #[test]
+    fn edge_frequency_finds_the_backbone() {
+        let cities = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![50.0, 20.0], vec![100.0, 0.0], vec![100.0, 1.0], vec![50.0, -20.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { max_unimproved: usize::MAX, ..seeded_config(10, 100) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let edge_frequency = count_edge_frequency(&result.solutions);
+        let backbone = [((0, 1), 5), ((0, 5), 5), ((1, 2), 5), ((2, 4), 5), ((3, 4), 5), ((3, 5), 5)];
+        assert_eq!(edge_frequency, backbone);
+    }
+
+    #[test]
+    fn gnuplot_data_closes_the_cycle() {
+        let cities = test_cities(7, 3);
+        let solution = vec![3, 1, 6, 0, 2, 5, 4];
+        let gnuplot_message = format_gnuplot(&solution, &cities);
+        let lines: Vec<&str> = gnuplot_message.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], format!("{} {}", cities[3][0], cities[3][1]));
+        assert_eq!(lines[7], lines[0]);
+    }
+
+    // The 4 by 3 rectangle has three distinct tours: the perimeter (14), one crossing pair of
+    // diagonals with the long sides (18) and one with the short sides (16).
+    #[test]
+    fn maximizing_finds_the_longest_tour() {
+        let cities = vec![vec![0.0, 0.0], vec![4.0, 0.0], vec![4.0, 3.0], vec![0.0, 3.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { optimize: OptimizeDirection::Max, ..seeded_config(4, 50) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert_eq!(result.best_solution_length, 18.0);
+        let minimizing_result = artificial_bee_colony(&distance, &distance, &cities, &seeded_config(4, 50), &[], None, false, false);
+        assert_eq!(minimizing_result.best_solution_length, 14.0);
+    }
+
+    // Counts how many of 200 sources move to a candidate one unit longer at the given temperature.
+    fn accepted_worsening_moves(temperature: f64) -> usize {
+        let config = ConfigKind { acceptance: AcceptanceMethod::SimulatedAnnealing, ..seeded_config(400, 10) };
+        let mut solutions = vec![vec![0, 1, 2]; 200];
+        let mut solutions_length = vec![10.0; 200];
+        let mut unimproved_times = vec![0; 200];
+        let new_solutions = vec![vec![0, 2, 1]; 200];
+        let new_solutions_length = vec![11.0; 200];
+        let mut rngs: Vec<SmallRng> = (0..200).map(|index| source_rng(&config, index)).collect();
+        let improved_sources = steady_state_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, &new_solutions, &new_solutions_length, temperature, &mut rngs, &config);
+        assert_eq!(improved_sources, 0);
+        assert!(unimproved_times.iter().all(|&times| times == 1));
+        solutions_length.iter().filter(|&&solution_length| solution_length == 11.0).count()
+    }
+
+    #[test]
+    fn annealing_accepts_worsening_moves_early_and_rarely_late() {
+        // exp(-1 / 100) is about 0.99 and exp(-1 / 0.1) about 0.00005.
+        assert!(accepted_worsening_moves(100.0) > 180);
+        assert!(accepted_worsening_moves(0.1) < 3);
+    }
+
+    #[test]
+    fn greedy_acceptance_keeps_improvements_only() {
+        let config = seeded_config(4, 10);
+        let mut solutions = vec![vec![0, 1, 2], vec![0, 1, 2]];
+        let mut solutions_length = vec![10.0, 10.0];
+        let mut unimproved_times = vec![3, 3];
+        let new_solutions = vec![vec![0, 2, 1], vec![0, 2, 1]];
+        let mut rngs: Vec<SmallRng> = (0..2).map(|index| source_rng(&config, index)).collect();
+        let improved_sources = steady_state_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, &new_solutions, &[9.0, 11.0], 1e9, &mut rngs, &config);
+        assert_eq!(improved_sources, 1);
+        assert_eq!(solutions_length, vec![9.0, 10.0]);
+        assert_eq!(unimproved_times, vec![0, 4]);
+    }
+
+    #[test]
+    fn diff_reports_length_change_and_differing_edges() {
+        let diff_message = format_diff(&test_data_path("diff_old.txt"), &test_data_path("diff_new.txt"));
+        assert_eq!(diff_message, "Length delta:-2\nLength change:-10%\nDiffering edges:2/6\n");
+    }
+
+    #[test]
+    fn limited_cities_shrink_the_matrix_and_tour() {
+        let output_path = env::temp_dir().join(format!("abc_limit_{}.txt", process::id()));
+        let matrix_path = env::temp_dir().join(format!("abc_limit_matrix_{}.csv", process::id()));
+        let arguments = ArgumentKind { limit_cities: Some(6), dump_matrix: Some(matrix_path.to_string_lossy().to_string()), ..default_arguments() };
+        solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 20), &arguments);
+        let solution = read_tour(&output_path.to_string_lossy());
+        let matrix_content = fs::read_to_string(&matrix_path).unwrap();
+        fs::remove_file(&output_path).ok();
+        fs::remove_file(&matrix_path).ok();
+        assert_eq!(solution.len(), 6);
+        assert!(validate_tour(&solution, 6).is_ok());
+        assert_eq!(matrix_content.lines().count(), 6);
+        assert!(matrix_content.lines().all(|line| line.split(',').count() == 6));
+    }
+
+    #[test]
+    fn replacement_counts_accepted_moves() {
+        let config = seeded_config(6, 10);
+        let mut solutions = vec![vec![0, 1, 2]; 3];
+        let mut solutions_length = vec![10.0, 10.0, 10.0];
+        let mut unimproved_times = vec![1, 1, 1];
+        let mut rngs: Vec<SmallRng> = (0..3).map(|index| source_rng(&config, index)).collect();
+        let improved_sources = steady_state_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, &[vec![0, 2, 1], vec![0, 2, 1], vec![0, 2, 1]], &[9.0, 11.0, 8.0], 1.0, &mut rngs, &config);
+        assert_eq!(improved_sources, 2);
+        assert_eq!(unimproved_times, vec![0, 2, 0]);
+        let mut solutions = vec![vec![0, 1, 2], vec![0, 2, 1]];
+        let mut solutions_length = vec![10.0, 12.0];
+        let mut unimproved_times = vec![2, 5];
+        let improved_sources = generational_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, vec![vec![1, 0, 2], vec![2, 1, 0]], vec![11.0, 13.0], &config);
+        assert_eq!(improved_sources, 1);
+        assert_eq!(solutions_length, vec![10.0, 11.0]);
+        assert_eq!(unimproved_times, vec![3, 0]);
+    }
+
+    #[test]
+    fn length_is_formatted_with_the_requested_decimals() {
+        let config = seeded_config(4, 10);
+        assert_eq!(format_length(2.0 / 3.0, &config, Some(3)), "0.667");
+        assert_eq!(format_length(12.0, &config, Some(2)), "12.00");
+        assert_eq!(format_length(12.5, &config, Some(0)), "12");
+        assert_eq!(format_length(2.0 / 3.0, &config, None), (2.0_f64 / 3.0).to_string());
+        let scaled_config = ConfigKind { distance_scale: 0.001, distance_unit: "km".to_string(), ..config };
+        assert_eq!(format_length(1234.5, &scaled_config, Some(1)), "1.2 km");
+    }
+
+    #[test]
+    fn cluster_seeded_tours_beat_random_ones_on_two_clusters() {
+        let mut cities = test_cities(10, 1);
+        cities.extend(test_cities(10, 2).into_iter().map(|city| vec![city[0] + 1000.0, city[1]]));
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let random_config = seeded_config(20, 10);
+        let cluster_config = ConfigKind { init_method: InitMethod::Cluster, cluster_count: Some(2), cluster_share: 1.0, ..seeded_config(20, 10) };
+        let (_, random_lengths) = initialize_phase(&distance, &cities, &random_config, 0);
+        let (cluster_solutions, cluster_lengths) = initialize_phase(&distance, &cities, &cluster_config, 0);
+        // Each seeded tour visits one cluster completely before the other, crossing the gap twice.
+        for solution in &cluster_solutions {
+            let crossings = (0..20).filter(|&i| (solution[i] < 10) != (solution[(i + 1) % 20] < 10)).count();
+            assert_eq!(crossings, 2);
+        }
+        let mean = |lengths: &[f64]| lengths.iter().sum::<f64>() / lengths.len() as f64;
+        assert!(mean(&cluster_lengths) < mean(&random_lengths));
+    }
+
+    #[test]
+    fn seed_tour_joins_the_initial_population() {
+        let cities = test_cities(20, 21);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let seed_tour = artificial_bee_colony(&distance, &distance, &cities, &seeded_config(10, 200), &[], None, false, false).best_solution;
+        let seed_tour_length = calc_path_length(&seed_tour, &distance);
+        let config = ConfigKind { seed: Some(8), ..seeded_config(10, 20) };
+        let (_, random_lengths) = initialize_phase(&distance, &cities, &config, 0);
+        assert!(random_lengths.iter().all(|&random_length| random_length > seed_tour_length));
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, std::slice::from_ref(&seed_tour), None, false, false);
+        assert_eq!(result.initial_best_length, seed_tour_length);
+        assert!(result.best_solution_length <= seed_tour_length);
+    }
+
+    #[test]
+    fn replace_worst_replaces_one_source_every_iteration() {
+        let cities = test_cities(15, 15);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { max_unimproved: usize::MAX, ..seeded_config(8, 40) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let replace_config = ConfigKind { replace_worst: true, ..config };
+        let replace_result = artificial_bee_colony(&distance, &distance, &cities, &replace_config, &[], None, false, false);
+        // Without scouts, the fresh tour is the only evaluation replace_worst adds per iteration.
+        assert_eq!(replace_result.evaluations - result.evaluations, 40);
+        assert_ne!(replace_result.solutions, result.solutions);
+    }
+
+    #[test]
+    fn dumped_matrix_round_trips_through_the_matrix_reader() {
+        let distance = calc_cities_distance(&test_cities(9, 6), DistanceMetric::Euclidean, DistanceRounding::None);
+        let matrix_path = env::temp_dir().join(format!("abc_dump_matrix_{}.csv", process::id()));
+        fs::write(&matrix_path, format_matrix(&distance)).unwrap();
+        let read_distance = read_distance_matrix(matrix_path.to_string_lossy().to_string(), 9, &(0..9).collect::<Vec<usize>>(), 0);
+        fs::remove_file(&matrix_path).ok();
+        for city1 in 0..9 {
+            for city2 in 0..9 {
+                assert_eq!(read_distance.get(city1, city2), distance.get(city1, city2));
+            }
+        }
+    }
+
+    #[test]
+    fn improvement_is_relative_or_absolute() {
+        let relative_config = ConfigKind { improvement_mode: ImprovementMode::Relative, ..seeded_config(4, 10) };
+        let absolute_config = ConfigKind { improvement_mode: ImprovementMode::Absolute, ..seeded_config(4, 10) };
+        assert_eq!(calc_improvement(190.0, 200.0, &relative_config), 0.05);
+        assert_eq!(calc_improvement(190.0, 200.0, &absolute_config), 10.0);
+        let max_config = ConfigKind { optimize: OptimizeDirection::Max, ..relative_config };
+        assert_eq!(calc_improvement(210.0, 200.0, &max_config), 0.05);
+    }
+
+    #[test]
+    fn absolute_threshold_above_one_is_accepted() {
+        validate_config(&ConfigKind { improvement_threshold: 5.0, improvement_mode: ImprovementMode::Absolute, ..seeded_config(4, 10) });
+    }
+
+    #[test]
+    #[should_panic(expected = "A relative threshold is a fraction between 0 and 1, use 0.05 for 5%.")]
+    fn percent_threshold_is_rejected_with_its_fraction() {
+        validate_config(&ConfigKind { improvement_threshold: 5.0, ..seeded_config(4, 10) });
+    }
+
+    #[test]
+    fn five_percent_threshold_stops_on_the_first_smaller_improvement() {
+        let cities = test_cities(30, 19);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { improvement_threshold: 0.05, ..seeded_config(10, 1000) };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert_eq!(result.stop_reason, StopReason::ImprovementThreshold);
+        let mut best_lengths: Vec<f64> = vec![result.initial_best_length];
+        best_lengths.extend(result.history.iter().map(|iteration| iteration.best_tour_length));
+        best_lengths.dedup();
+        let improvements: Vec<f64> = best_lengths.windows(2).map(|pair| (pair[0] - pair[1]) / pair[0]).collect();
+        let (last_improvement, earlier_improvements) = improvements.split_last().unwrap();
+        assert!(*last_improvement < 0.05);
+        assert!(earlier_improvements.iter().all(|&improvement| improvement >= 0.05));
+    }
+
+    #[test]
+    fn no_improvement_timeout_stops_a_plateaued_run() {
+        let cities = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { no_improve_secs: Some(0.05), ..seeded_config(4, usize::MAX) };
+        let start_time = Instant::now();
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert_eq!(result.stop_reason, StopReason::Stagnant);
+        assert_eq!(result.best_solution_length, 4.0);
+        assert!(start_time.elapsed() >= Duration::from_millis(50));
+        assert!(start_time.elapsed() < Duration::from_secs(10));
+    }
+
+    // The matrix makes the hexagon's sides, its geometrically shortest tour, cost 10 and every
+    // other edge 1, so the search must follow the matrix rather than the coordinates.
+    #[test]
+    fn matrix_costs_override_the_coordinates() {
+        let output_path = env::temp_dir().join(format!("abc_matrix_{}.txt", process::id()));
+        let arguments = ArgumentKind { matrix: Some(test_data_path("hexagon_matrix.csv")), ..default_arguments() };
+        let best_solution_length = solve_instance(test_data_path("hexagon.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 100), &arguments);
+        let solution = read_tour(&output_path.to_string_lossy());
+        fs::remove_file(&output_path).ok();
+        assert_eq!(best_solution_length, 6.0);
+        let hexagon_sides = tour_edges(&[0, 1, 2, 3, 4, 5]);
+        assert!(tour_edges(&solution).is_disjoint(&hexagon_sides));
+    }
+
+    #[test]
+    #[should_panic(expected = "The matrix is 5x6, expected 6x6 for the input cities.")]
+    fn matrix_of_another_size_is_rejected() {
+        let output_path = env::temp_dir().join(format!("abc_short_matrix_{}.txt", process::id()));
+        let arguments = ArgumentKind { matrix: Some(test_data_path("hexagon_short_matrix.csv")), ..default_arguments() };
+        solve_instance(test_data_path("hexagon.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 100), &arguments);
+    }
+
+    #[test]
+    fn more_onlookers_never_select_a_worse_candidate() {
+        let candidate_length = [7.0, 3.0, 9.0, 5.0, 4.0, 8.0];
+        for seed in 0..50 {
+            let selected_lengths: Vec<f64> = [1, 2, 4, 8]
+                .iter()
+                .map(|&onlooker_count| {
+                    let config = ConfigKind { onlooker_count, tournament_size: 2, ..seeded_config(4, 10) };
+                    candidate_length[select_candidate(&candidate_length, &mut SmallRng::seed_from_u64(seed), &config)]
+                })
+                .collect();
+            assert!(selected_lengths.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", selected_lengths);
+        }
+    }
+
+    #[test]
+    fn stream_writes_one_json_line_per_iteration() {
+        let cities = test_cities(12, 23);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let stream_path = env::temp_dir().join(format!("abc_stream_{}.jsonl", process::id()));
+        let mut stream = StreamKind { file: File::create(&stream_path).unwrap(), interval: 1 };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &seeded_config(8, 25), &[], Some(&mut stream), false, false);
+        let stream_content = fs::read_to_string(&stream_path).unwrap();
+        fs::remove_file(&stream_path).ok();
+        assert_eq!(result.history.len(), 25);
+        assert_eq!(stream_content.lines().count(), 25);
+        for (index, line) in stream_content.lines().enumerate() {
+            let fields: Vec<(&str, f64)> = line
+                .strip_prefix('{')
+                .and_then(|line| line.strip_suffix('}'))
+                .unwrap()
+                .split(',')
+                .map(|field| {
+                    let (key, value) = field.split_once(':').unwrap();
+                    (key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap(), value.parse::<f64>().unwrap())
+                })
+                .collect();
+            let keys: Vec<&str> = fields.iter().map(|(key, _)| *key).collect();
+            assert_eq!(keys, ["iter", "best", "mean", "diversity", "accept_rate"]);
+            assert_eq!(fields[0].1, (index + 1) as f64);
+            assert_eq!(fields[1].1, result.history[index].best_tour_length);
+        }
+    }
+
+    #[test]
+    fn stats_report_the_unit_square_bounding_box() {
+        let output_path = env::temp_dir().join(format!("abc_stats_{}.txt", process::id()));
+        let arguments = ArgumentKind { stats: true, ..default_arguments() };
+        solve_instance(test_data_path("unit_square.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 50), &arguments);
+        let output_content = fs::read_to_string(&output_path).unwrap();
+        fs::remove_file(&output_path).ok();
+        // The best tour visits the centre from one side: 3 + 2 * sqrt(0.5) over a perimeter of 4.
+        let best_length = 3.0 + 2.0 * 0.5_f64.sqrt();
+        assert!(output_content.contains("Bounding box:1x1\n"), "{}", output_content);
+        assert!(output_content.contains(&format!("Length to bounding box perimeter:{}\n", best_length / 4.0)), "{}", output_content);
+        assert!(output_content.contains(&format!("Average edge length:{}\n", best_length / 5.0)), "{}", output_content);
+    }
+
+    // With every edge costing the same, selection cannot tell candidates apart, so the picked
+    // candidate derives from the elite exactly as often as any candidate does.
+    #[test]
+    fn elite_candidates_appear_at_the_configured_rate() {
+        let cities = test_cities(20, 2);
+        let distance = DistanceMatrix::Full(vec![vec![1.0; 20]; 20]);
+        let config = ConfigKind { elite_mutation_rate: 0.25, generation_method: GenerationMethod::Swap, ..seeded_config(4, 10) };
+        let solution: Vec<usize> = (0..20).collect();
+        let elite_solution: Vec<usize> = (0..20).map(|position| (position + 5) % 20).collect();
+        let mut rng = SmallRng::seed_from_u64(66);
+        let elite_picks = (0..4000)
+            .filter(|_| {
+                let candidate = employed_bee(&solution, &elite_solution, 0.0, &mut rng, &distance, &cities, &config).0;
+                (0..20).filter(|&position| candidate[position] != elite_solution[position]).count() <= 2
+            })
+            .count();
+        assert!((900..=1100).contains(&elite_picks), "{}", elite_picks);
+    }
+
+    // Counts how many of 500 swap moves only exchange focus cities.
+    fn focus_only_moves(focus_probability: f64) -> usize {
+        let cities = test_cities(20, 12);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { generation_method: GenerationMethod::Swap, focus_cities: vec![3, 7, 11], ..seeded_config(4, 10) };
+        let mut rng = SmallRng::seed_from_u64(68);
+        let mut solution: Vec<usize> = (0..20).collect();
+        solution.shuffle(&mut rng);
+        (0..500)
+            .filter(|_| {
+                let candidate = employed_bee(&solution, &solution, focus_probability, &mut rng, &distance, &cities, &config).0;
+                (0..20).filter(|&position| candidate[position] != solution[position]).all(|position| config.focus_cities.contains(&solution[position]))
+            })
+            .count()
+    }
+
+    #[test]
+    fn early_moves_involve_the_focus_cities() {
+        // The first iteration uses focus_probability 1; random swaps hit two of three focus cities
+        // in 20 about 2% of the time.
+        assert_eq!(focus_only_moves(1.0), 500);
+        assert!(focus_only_moves(0.0) < 50);
+    }
+
+    #[test]
+    fn population_export_has_one_row_per_source() {
+        let output_path = env::temp_dir().join(format!("abc_population_out_{}.txt", process::id()));
+        let population_path = env::temp_dir().join(format!("abc_population_out_{}.csv", process::id()));
+        let arguments = ArgumentKind { population_out: Some(population_path.to_string_lossy().to_string()), ..default_arguments() };
+        solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &seeded_config(12, 20), &arguments);
+        let population_content = fs::read_to_string(&population_path).unwrap();
+        let population = read_population(&population_path.to_string_lossy(), 16);
+        fs::remove_file(&output_path).ok();
+        fs::remove_file(&population_path).ok();
+        assert_eq!(population_content.lines().count(), 6);
+        assert_eq!(population.len(), 6);
+        assert!(population.iter().all(|solution| validate_tour(solution, 16).is_ok()));
+    }
+
+    #[test]
+    fn imported_population_is_at_least_as_good_as_the_export() {
+        let output_path = env::temp_dir().join(format!("abc_population_in_{}.txt", process::id()));
+        let population_path = env::temp_dir().join(format!("abc_population_in_{}.csv", process::id()));
+        let population_path_format = population_path.to_string_lossy().to_string();
+        let export_arguments = ArgumentKind { population_out: Some(population_path_format.clone()), ..default_arguments() };
+        solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &seeded_config(12, 30), &export_arguments);
+        let best_exported_length = fs::read_to_string(&population_path)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(',').next().unwrap().parse::<f64>().unwrap())
+            .fold(f64::INFINITY, f64::min);
+        let import_arguments = ArgumentKind { population_in: Some(population_path_format), ..default_arguments() };
+        let config = ConfigKind { seed: Some(70), ..seeded_config(12, 5) };
+        let best_imported_length = solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &config, &import_arguments);
+        fs::remove_file(&output_path).ok();
+        fs::remove_file(&population_path).ok();
+        assert!(best_imported_length <= best_exported_length);
+    }
+
+    #[test]
+    fn no_optimize_reports_the_initial_tour_without_iterating() {
+        let output_path = env::temp_dir().join(format!("abc_no_optimize_{}.txt", process::id()));
+        let arguments = ArgumentKind { no_optimize: true, ..default_arguments() };
+        let best_solution_length = solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 100), &arguments);
+        let output_content = fs::read_to_string(&output_path).unwrap();
+        let solution = read_tour(&output_path.to_string_lossy());
+        fs::remove_file(&output_path).ok();
+        assert!(output_content.contains("Stop reason:NoOptimize\n"), "{}", output_content);
+        assert!(validate_tour(&solution, 16).is_ok());
+        let cities = read_input(test_data_path("golden.csv"), 0);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        assert_eq!(best_solution_length, calc_path_length(&solution, &distance));
+    }
+
+    #[test]
+    fn optimal_gap_between_two_known_tours() {
+        let cities = vec![vec![0.0, 0.0], vec![4.0, 0.0], vec![4.0, 3.0], vec![0.0, 3.0]];
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        // The crossing tour (18) shares its two long sides with the perimeter (14).
+        let crossing_tour = [0, 1, 3, 2];
+        let gap_message = format_optimal_gap(&crossing_tour, 18.0, &[0, 1, 2, 3], &distance, &seeded_config(4, 10), Some(2));
+        let expected_message = format!("Optimal length:14.00\nGap to optimal:4.00\nGap to optimal percent:{}%\nShared optimal edges:2/4\n", 4.0 / 14.0 * 100.0);
+        assert_eq!(gap_message, expected_message);
+    }
+
+    #[test]
+    fn tsplib_tour_files_are_read_zero_based() {
+        let optimal_tour = read_tour(&test_data_path("berlin52.opt.tour"));
+        assert_eq!(optimal_tour.len(), 52);
+        assert_eq!(optimal_tour[..3], [0, 48, 31]);
+        assert!(validate_tour(&optimal_tour, 52).is_ok());
+        assert_eq!(read_tsplib_tour("TOUR_SECTION\n2\n1\n3\n-1\nEOF\n"), Some(vec![1, 0, 2]));
+        assert_eq!(read_tsplib_tour("0 1 2\n"), None);
+    }
+
+    // Seeded runs of 1, 2, ... iterations replay the same trajectory, so the best source of each
+    // final population traces the generational population's best over the iterations.
+    #[test]
+    fn generational_population_best_never_worsens() {
+        let cities = test_cities(15, 27);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let population_bests: Vec<f64> = (1..=30)
+            .map(|max_iterations| {
+                let config = ConfigKind { replacement: ReplacementMethod::Generational, max_unimproved: usize::MAX, ..seeded_config(8, max_iterations) };
+                let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+                result.solutions.iter().map(|solution| calc_path_length(solution, &distance)).fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        assert!(population_bests.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", population_bests);
+        assert!(population_bests[29] < population_bests[0]);
+    }
+
+    #[test]
+    fn candidate_amount_follows_the_schedule() {
+        let linear_config = ConfigKind { candidate_amount: 10, candidate_amount_end: Some(2), candidate_schedule: CandidateSchedule::Linear, ..seeded_config(20, 9) };
+        let linear_amounts: Vec<usize> = (1..=9).map(|iteration| scheduled_candidate_amount(iteration, &linear_config)).collect();
+        assert_eq!(linear_amounts, [10, 9, 8, 7, 6, 5, 4, 3, 2]);
+        let exp_config = ConfigKind { candidate_amount: 16, candidate_amount_end: Some(1), candidate_schedule: CandidateSchedule::Exp, ..seeded_config(20, 5) };
+        let exp_amounts: Vec<usize> = (1..=5).map(|iteration| scheduled_candidate_amount(iteration, &exp_config)).collect();
+        assert_eq!(exp_amounts, [16, 8, 4, 2, 1]);
+        // Each of the 10 sources evaluates its candidates, the delta base and the re-evaluated source.
+        let cities = test_cities(12, 29);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = ConfigKind { max_unimproved: usize::MAX, ..linear_config };
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        assert_eq!(result.evaluations, 10 + linear_amounts.iter().map(|candidate_amount| 10 * (candidate_amount + 2)).sum::<usize>());
+    }
+
+    #[test]
+    fn output_files_create_missing_parent_directories() {
+        let nested_dir = env::temp_dir().join(format!("abc_nested_{}", process::id()));
+        let output_path = nested_dir.join("results").join("run.txt");
+        let history_path = nested_dir.join("history").join("run.csv");
+        let arguments = ArgumentKind { history: Some(history_path.to_string_lossy().to_string()), ..default_arguments() };
+        solve_instance(test_data_path("golden.csv"), output_path.to_string_lossy().to_string(), &seeded_config(10, 20), &arguments);
+        let output_exists = output_path.is_file();
+        let history_exists = history_path.is_file();
+        fs::remove_dir_all(&nested_dir).ok();
+        assert!(output_exists);
+        assert!(history_exists);
+    }
+
+    #[test]
+    fn anytime_lengths_never_increase_across_checkpoints() {
+        let cities = test_cities(20, 31);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = seeded_config(10, 200);
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let anytime_message = format_anytime(&result.history, result.best_solution_length, &ANYTIME_CHECKPOINTS, &config, None);
+        let anytime_lengths: Vec<f64> = anytime_message
+            .split(' ')
+            .map(|checkpoint| checkpoint.split_once('=').unwrap().1.parse::<f64>().unwrap())
+            .collect();
+        assert_eq!(anytime_lengths.len(), ANYTIME_CHECKPOINTS.len());
+        assert!(anytime_lengths.windows(2).all(|pair| pair[1] <= pair[0]), "{}", anytime_message);
+        assert_eq!(*anytime_lengths.last().unwrap(), result.best_solution_length);
+        assert!(anytime_lengths[0] > result.best_solution_length);
+    }
+
+    #[test]
+    fn two_and_three_city_instances_are_trivial() {
+        for (cities_content, expected_length) in [("0,0\n3,4\n", 10.0), ("0,0\n3,0\n0,4\n", 12.0)] {
+            let input_path = env::temp_dir().join(format!("abc_trivial_{}.csv", process::id()));
+            let output_path = env::temp_dir().join(format!("abc_trivial_{}.txt", process::id()));
+            fs::write(&input_path, cities_content).unwrap();
+            let best_solution_length = solve_instance(input_path.to_string_lossy().to_string(), output_path.to_string_lossy().to_string(), &seeded_config(10, 100), &default_arguments());
+            let output_content = fs::read_to_string(&output_path).unwrap();
+            fs::remove_file(&input_path).ok();
+            fs::remove_file(&output_path).ok();
+            assert_eq!(best_solution_length, expected_length);
+            assert!(output_content.contains("Stop reason:Trivial\n"), "{}", output_content);
+        }
+    }
+
+    // The same weight keeps the fixed edge on a unit circle and on one a thousand times larger, while
+    // an absolute penalty of that size vanishes next to the larger circle's edges.
+    #[test]
+    fn average_edge_penalty_is_scale_independent() {
+        let circle = |radius: f64| -> Vec<Vec<f64>> {
+            (0..8).map(|city| {
+                let angle = city as f64 * std::f64::consts::PI / 4.0;
+                vec![radius * angle.cos(), radius * angle.sin()]
+            }).collect()
+        };
+        let config = ConfigKind {
+            fixed_edges: vec![(0, 4)],
+            fixed_edge_penalty: 5.0,
+            constraint_handling: ConstraintHandling::Penalty,
+            penalty_scale: PenaltyScale::AverageEdge,
+            ..seeded_config(10, 50)
+        };
+        let mut rates = Vec::new();
+        for radius in [1.0, 1000.0] {
+            let cities = circle(radius);
+            let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+            let search_config = scale_penalties(config.clone(), &config, &distance);
+            let result = artificial_bee_colony(&distance, &distance, &cities, &search_config, &[], None, false, false);
+            assert_eq!(count_missing_fixed_edges(&result.best_solution, &config.fixed_edges), 0);
+            rates.push(feasibility_rate(&result.solutions, &distance, &config));
+        }
+        assert!((rates[0] - rates[1]).abs() <= 0.1, "{:?}", rates);
+        let absolute_config = ConfigKind { penalty_scale: PenaltyScale::Absolute, ..config };
+        let cities = circle(1000.0);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let search_config = scale_penalties(absolute_config.clone(), &absolute_config, &distance);
+        let result = artificial_bee_colony(&distance, &distance, &cities, &search_config, &[], None, false, false);
+        assert_eq!(count_missing_fixed_edges(&result.best_solution, &absolute_config.fixed_edges), 1);
+    }
+
+    #[test]
+    fn sub_epsilon_gain_does_not_reset_unimproved_times() {
+        let config = seeded_config(2, 10);
+        let run = |config: &ConfigKind| {
+            let mut solutions = vec![vec![0, 1, 2, 3], vec![0, 1, 2, 3]];
+            let mut solutions_length = vec![100.0, 100.0];
+            let mut unimproved_times = vec![3, 3];
+            let new_solutions = vec![vec![0, 2, 1, 3], vec![0, 3, 2, 1]];
+            let mut rngs: Vec<SmallRng> = (0..2).map(SmallRng::seed_from_u64).collect();
+            let improved_sources = steady_state_replacement(&mut solutions, &mut solutions_length, &mut unimproved_times, &new_solutions, &[100.0 - 1e-12, 90.0], 1.0, &mut rngs, config);
+            (improved_sources, solutions_length, unimproved_times)
+        };
+        assert_eq!(run(&config), (1, vec![100.0, 90.0], vec![4, 0]));
+        let exact_config = ConfigKind { improvement_epsilon: 0.0, ..config };
+        assert_eq!(run(&exact_config), (2, vec![100.0 - 1e-12, 90.0], vec![0, 0]));
+    }
+
+    #[test]
+    fn mapped_columns_use_only_spatial_columns_for_distance() {
+        let columns: Vec<String> = ["id", "x", "y", "demand"].iter().map(|column| column.to_string()).collect();
+        let (cities, labels) = read_mapped_input(test_data_path("mixed_columns.csv"), &columns, 0);
+        assert_eq!(cities, vec![vec![0.0, 0.0], vec![3.0, 4.0], vec![0.0, 8.0]]);
+        assert_eq!(labels, Some(vec!["depot".to_string(), "north".to_string(), "far".to_string()]));
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        assert_eq!((distance.get(0, 1), distance.get(1, 2), distance.get(0, 2)), (5.0, 5.0, 8.0));
+    }
+
+    // concurrent_count = 1 stands in for a container that misreports a single CPU.
+    #[test]
+    fn threads_argument_overrides_concurrent_count() {
+        let config_path = test_data_path("single_thread_config.txt");
+        assert_eq!(resolve_config(config_path.clone(), &default_arguments()).concurrent_count, 1);
+        let arguments = get_arguments(command_line(&["--threads=4"]));
+        assert_eq!(resolve_config(config_path, &arguments).concurrent_count, 4);
+    }
+
+    #[test]
+    fn iter_timing_adds_plausible_cost_time_column() {
+        let cities = test_cities(20, 3);
+        let distance = calc_cities_distance(&cities, DistanceMetric::Euclidean, DistanceRounding::None);
+        let config = seeded_config(20, 30);
+        let run_start = Instant::now();
+        let result = artificial_bee_colony(&distance, &distance, &cities, &config, &[], None, false, false);
+        let run_time = run_start.elapsed().as_micros();
+        let history_content = format_history(&result.history, &config, true);
+        let mut lines = history_content.lines();
+        assert_eq!(lines.next(), Some("iteration,best_solution_length,improved_sources,acceptance_rate,cost_time_us"));
+        let cost_times: Vec<u128> = lines.map(|line| line.rsplit(',').next().unwrap().parse::<u128>().unwrap()).collect();
+        assert_eq!(cost_times.len(), result.history.len());
+        assert!(cost_times.iter().sum::<u128>() > 0);
+        assert!(cost_times.iter().sum::<u128>() <= run_time, "{:?} {}", cost_times, run_time);
+        assert!(!format_history(&result.history, &config, false).contains("cost_time_us"));
+    }
 }